@@ -1,262 +1,275 @@
+use std::{
+    net::{IpAddr, Ipv6Addr},
+    path::PathBuf,
+    time::Duration,
+};
+
 use bevy::{
-    color::palettes::css::GREEN,
+    app::ScheduleRunnerPlugin,
+    input::InputPlugin,
+    log::LogPlugin,
     prelude::*,
+    state::app::StatesPlugin,
+    window::WindowResolution,
     winit::{UpdateMode::Continuous, WinitSettings},
 };
-use bevy_quinnet::{
-    client::{
-        QuinnetClient, certificate::CertificateVerificationMode,
-        connection::ClientEndpointConfiguration,
+use clap::{Args, Parser, Subcommand};
+use first_bevy_game::{
+    net::{
+        ChannelId, ClientSettings, DEFAULT_MAX_CLIENTS, DEFAULT_PORT, InterpolationSettings,
+        LinkConditions, NetPongPlugin, NetRole, NetworkConditions, PlayerInfo, ServerCertificate,
+        ServerSettings, default_known_hosts_file,
     },
-    server::{QuinnetServer, ServerEndpointConfiguration, certificate::CertificateRetrievalMode},
-};
-use bevy_replicon::prelude::*;
-use bevy_replicon_quinnet::{ChannelsConfigurationExt, RepliconQuinnetPlugins};
-use clap::{Parser, Subcommand};
-use serde::{Deserialize, Serialize};
-use std::{
-    error::Error,
-    hash::{DefaultHasher, Hash, Hasher},
-    net::{IpAddr, Ipv6Addr},
+    pong::BASE,
 };
 
-const PORT: u16 = 5000;
-
 fn main() {
+    let cli = Cli::parse();
+    let net_pong = NetPongPlugin {
+        role: cli.role(),
+        interpolation: cli.interpolation(),
+        conditions: cli.conditions(),
+        player: PlayerInfo {
+            name: cli.name.clone(),
+            color: cli.color,
+        },
+        ..default()
+    };
+
+    if cli.headless() {
+        // A dedicated server for a machine without a display, e.g. in a
+        // container, ticking at the pong simulation rate
+        App::new()
+            .add_plugins((
+                MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                    1.0 / 60.0,
+                ))),
+                LogPlugin::default(),
+                StatesPlugin,
+                InputPlugin,
+                net_pong,
+            ))
+            .run();
+        return;
+    }
+
     App::new()
-        .init_resource::<Cli>()
+        // Keep the match running while another window has focus, the other
+        // side of the connection is usually one
         .insert_resource(WinitSettings {
             focused_mode: Continuous,
             unfocused_mode: Continuous,
         })
+        .insert_resource(ClearColor(BASE))
         .add_plugins((
-            DefaultPlugins,
-            RepliconPlugins,
-            RepliconQuinnetPlugins,
-            HelloPlugin,
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: WindowResolution::new(1000., 1000.).with_scale_factor_override(1.0),
+                    ..default()
+                }),
+                ..default()
+            }),
+            net_pong,
         ))
         .run();
 }
 
-#[derive(Parser, PartialEq, Resource)]
-#[command(version, about, long_about = None)]
+#[derive(Parser, PartialEq)]
+#[command(version, about = "Pong over the network", long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
     #[command(subcommand)]
     network_mode: Option<NetworkMode>,
+
+    /// Name shown to the other players in the lobby
+    #[arg(long, global = true, default_value = "Player")]
+    name: String,
+
+    /// Colour of your paddle and name, as hex like `f38ba8`
+    #[arg(long, global = true, default_value = "89b4fa", value_parser = parse_color)]
+    color: [u8; 3],
+}
+
+fn parse_color(hex: &str) -> Result<[u8; 3], String> {
+    let hex = hex.trim_start_matches('#');
+    let channel = |at: usize| {
+        hex.get(at..at + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("`{hex}` isn't a colour like `f38ba8`")),
+    }
 }
 
-// #[derive(Parser, PartialEq, Resource)]
 #[derive(Subcommand, PartialEq)]
 enum NetworkMode {
+    /// Play against the computer, through a server and client talking over
+    /// an in-memory loopback instead of the network
     SinglePlayer,
+    /// Host a match and play the left paddle once everyone is ready
     Server {
-        #[arg(short, long, default_value_t = PORT)]
+        #[arg(short, long, default_value_t = DEFAULT_PORT)]
         port: u16,
-    },
-    Client {
-        #[arg(short, long, default_value_t = Ipv6Addr::LOCALHOST.into())]
-        ip: IpAddr,
 
-        #[arg(short, long, default_value_t = PORT)]
-        port: u16,
-    },
-}
+        /// Address to listen on, `::` for every interface
+        #[arg(short, long, default_value_t = Ipv6Addr::LOCALHOST.into())]
+        bind: IpAddr,
 
-impl Default for Cli {
-    fn default() -> Self {
-        Self::parse()
-    }
-}
+        /// Clients let in at once, the first two play and the rest watch
+        #[arg(long, default_value_t = DEFAULT_MAX_CLIENTS)]
+        max_clients: usize,
 
-#[derive(Component)]
-struct Person;
+        /// PEM certificate to present to clients instead of the self-signed
+        /// one kept in the data directory
+        #[arg(long, requires = "key")]
+        cert: Option<PathBuf>,
 
-#[derive(Component)]
-struct Name(String);
+        /// PEM private key of `--cert`
+        #[arg(long, requires = "cert")]
+        key: Option<PathBuf>,
 
-#[derive(Resource)]
-struct GreetTimer(Timer);
+        /// Make up a new self-signed certificate every launch instead of
+        /// keeping one, clients that pinned the old one will refuse it
+        #[arg(long, conflicts_with = "cert")]
+        ephemeral_cert: bool,
 
-fn add_people(mut commands: Commands) {
-    commands.spawn((Person, Name("Grant Wiswell".to_string())));
-    commands.spawn((Person, Name("Chloe Crichton".to_string())));
-    commands.spawn((Person, Name("Ty Lee Wiswell".to_string())));
-    commands.spawn((Person, Name("Mai Wiswell".to_string())));
-}
+        /// Run without a window or a local player, leaving both paddles to
+        /// clients
+        #[arg(long)]
+        headless: bool,
 
-fn greet_people(time: Res<Time>, mut timer: ResMut<GreetTimer>, query: Query<&Name, With<Person>>) {
-    if timer.0.tick(time.delta()).just_finished() {
-        for name in &query {
-            info!("hello {}!", name.0)
-        }
-    }
-}
+        #[command(flatten)]
+        conditions: ConditionArgs,
+    },
+    /// Join a match, playing the right paddle if it is free
+    Client {
+        #[arg(short, long, default_value_t = Ipv6Addr::LOCALHOST.into())]
+        ip: IpAddr,
 
-fn update_people(mut query: Query<&mut Name, With<Person>>) {
-    for mut name in &mut query {
-        if name.0 == "Chloe Crichton" {
-            name.0 = "Chloe Wiswell".to_string();
-            break;
-        }
-    }
-}
+        #[arg(short, long, default_value_t = DEFAULT_PORT)]
+        port: u16,
 
-pub struct HelloPlugin;
+        /// How far in the past to show the ball and the other paddle, in
+        /// milliseconds. Longer rides out worse connections
+        #[arg(long, default_value_t = 100)]
+        interpolation_delay: u64,
 
-impl Plugin for HelloPlugin {
-    fn build(&self, app: &mut App) {
-        app.insert_resource(GreetTimer(Timer::from_seconds(2.0, TimerMode::Repeating)));
-        app.add_systems(
-            Startup,
-            (read_cli.map(Result::unwrap), add_people, spawn_camera),
-        );
-        app.add_systems(Update, (update_people, greet_people).chain());
-        app.add_systems(Update, (draw_boxes));
-    }
-}
+        /// File of server fingerprints trusted so far, kept in the data
+        /// directory by default
+        #[arg(long)]
+        known_hosts: Option<PathBuf>,
 
-fn read_cli(
-    mut commands: Commands,
-    cli: Res<Cli>,
-    channels: Res<RepliconChannels>,
-    mut server: ResMut<QuinnetServer>,
-    mut client: ResMut<QuinnetClient>,
-) -> Result<(), Box<dyn Error>> {
-    match &cli.network_mode {
-        Some(mode) => match mode {
-            NetworkMode::SinglePlayer => start_singleplayer(commands),
-            NetworkMode::Server { port } => start_server(server, channels, *port, commands),
-            NetworkMode::Client { ip, port } => {
-                start_client(*ip, *port, channels, commands, client)
-            }
-        },
-        None => start_singleplayer(commands),
-    }
-    Ok(())
-}
+        /// Fingerprint the server is expected to present, as printed by it on
+        /// start. Replaces one pinned before, e.g. after the server changed
+        /// its certificate
+        #[arg(long)]
+        trust: Option<String>,
 
-#[derive(Component, Deref, Deserialize, Serialize, Default)]
-#[require(BoxPosition, Replicated)]
-struct PlayerBox {
-    color: Color,
+        #[command(flatten)]
+        conditions: ConditionArgs,
+    },
 }
 
-#[derive(Component, Deserialize, Serialize, Deref, DerefMut, Default)]
-struct BoxPosition(Vec2);
+/// A bad connection to simulate, press F2 while playing to change it.
+#[derive(Args, PartialEq)]
+struct ConditionArgs {
+    /// Latency, jitter, loss, duplication and reordering of every channel,
+    /// like `latency=80,jitter=20,loss=5`, in milliseconds and percent
+    #[arg(long)]
+    conditions: Option<LinkConditions>,
 
-#[derive(Component, Clone, Copy, Deref)]
-struct BoxOwner(Entity);
+    /// Conditions of a single channel in place of `--conditions`, like
+    /// `to-server/2:loss=30`. Can be given more than once
+    #[arg(long, value_parser = parse_channel_conditions)]
+    channel_conditions: Vec<(ChannelId, LinkConditions)>,
 
-fn spawn_camera(mut commands: Commands) {
-    commands.spawn(Camera2d);
+    /// Seed deciding what happens to each message, to repeat a run
+    #[arg(long)]
+    conditions_seed: Option<u64>,
 }
 
-fn draw_boxes(mut gizmos: Gizmos, boxes: Query<(&BoxPosition, &PlayerBox)>) {
-    for (position, player) in &boxes {
-        gizmos.rect(
-            Vec3::new(position.x, position.y, 0.0),
-            Vec2::ONE * 50.0,
-            player.color,
-        );
-    }
+fn parse_channel_conditions(arg: &str) -> Result<(ChannelId, LinkConditions), String> {
+    let (channel, conditions) = arg
+        .split_once(':')
+        .ok_or_else(|| format!("`{arg}` should look like `to-server/2:loss=30`"))?;
+    Ok((channel.parse()?, conditions.parse()?))
 }
 
-fn start_server(
-    mut server: ResMut<QuinnetServer>,
-    channels: Res<RepliconChannels>,
-    port: u16,
-    mut commands: Commands,
-) {
-    server
-        .start_endpoint(
-            ServerEndpointConfiguration::from_ip(Ipv6Addr::LOCALHOST, port),
-            CertificateRetrievalMode::GenerateSelfSigned {
-                server_hostname: Ipv6Addr::LOCALHOST.to_string(),
-            },
-            channels.server_configs(),
-        )
-        .unwrap();
-
-    commands.spawn((
-        Text::new("Server"),
-        TextFont {
-            font_size: 30.0,
-            ..Default::default()
-        },
-        TextColor::WHITE,
-    ));
+impl Cli {
+    fn role(&self) -> NetRole {
+        match self.network_mode {
+            None | Some(NetworkMode::SinglePlayer) => NetRole::SinglePlayer,
+            Some(NetworkMode::Server {
+                port,
+                bind,
+                max_clients,
+                ref cert,
+                ref key,
+                ephemeral_cert,
+                headless,
+                ..
+            }) => NetRole::Server(ServerSettings {
+                bind,
+                port,
+                max_clients,
+                certificate: match (cert, key) {
+                    (Some(cert), Some(key)) => ServerCertificate::Files {
+                        cert: cert.clone(),
+                        key: key.clone(),
+                    },
+                    _ if ephemeral_cert => ServerCertificate::SelfSigned,
+                    _ => ServerCertificate::in_data_dir(),
+                },
+                dedicated: headless,
+                ..default()
+            }),
+            Some(NetworkMode::Client {
+                ip,
+                port,
+                ref known_hosts,
+                ref trust,
+                ..
+            }) => NetRole::Client(ClientSettings {
+                ip,
+                port,
+                known_hosts: known_hosts.clone().or_else(default_known_hosts_file),
+                trust: trust.clone(),
+                ..default()
+            }),
+        }
+    }
 
-    commands.spawn((
-        PlayerBox {
-            color: GREEN.into(),
-        },
-        BoxOwner(SERVER),
-    ));
-}
+    fn conditions(&self) -> NetworkConditions {
+        let (Some(NetworkMode::Server { conditions, .. })
+        | Some(NetworkMode::Client { conditions, .. })) = &self.network_mode
+        else {
+            return default();
+        };
+        NetworkConditions {
+            all: conditions.conditions.unwrap_or_default(),
+            channels: conditions.channel_conditions.iter().copied().collect(),
+            seed: conditions.conditions_seed,
+        }
+    }
 
-fn start_client(
-    ip: IpAddr,
-    port: u16,
-    channels: Res<RepliconChannels>,
-    mut commands: Commands,
-    mut client: ResMut<QuinnetClient>,
-) {
-    client
-        .open_connection(
-            ClientEndpointConfiguration::from_ips(ip, port, Ipv6Addr::UNSPECIFIED, 0),
-            CertificateVerificationMode::SkipVerification,
-            channels.client_configs(),
+    fn headless(&self) -> bool {
+        matches!(
+            self.network_mode,
+            Some(NetworkMode::Server { headless: true, .. })
         )
-        .unwrap();
-    commands.spawn((
-        Text("Client".into()),
-        TextFont {
-            font_size: 30.0,
-            ..default()
-        },
-        TextColor::WHITE,
-    ));
-
-    info!("Client started. Connected to {}", ip)
-}
-
-fn spawn_clients(trigger: Trigger<OnAdd, ConnectedClient>, mut commands: Commands) {
-    let mut hasher = DefaultHasher::new();
-    trigger.entity().index().hash(&mut hasher);
-    let hash = hasher.finish();
-
-    let r = ((hash >> 16) & 0xFF) as f32 / 255.0;
-    let g = ((hash >> 8) & 0xFF) as f32 / 255.0;
-    let b = (hash & 0xFF) as f32 / 255.0;
-
-    info!("Spawning box for `{}`", trigger.entity());
-    commands.spawn((
-        PlayerBox {
-            color: Color::srgb(r, g, b),
-        },
-        BoxOwner(trigger.entity()),
-    ));
-}
-
-fn despawn_clients(
-    trigger: Trigger<OnRemove, ConnectedClient>,
-    mut commands: Commands,
-    boxes: Query<(Entity, &BoxOwner)>,
-) {
-    let (entity, _) = boxes
-        .iter()
-        .find(|(_, &owner)| *owner == trigger.entity())
-        .expect("all clients should have entites");
-    commands.entity(entity).despawn();
-}
+    }
 
-fn start_singleplayer(mut commands: Commands) {
-    info!("Starting Singleplayer!");
-    commands.spawn((
-        PlayerBox {
-            color: GREEN.into(),
-        },
-        BoxOwner(SERVER),
-    ));
+    fn interpolation(&self) -> InterpolationSettings {
+        match self.network_mode {
+            Some(NetworkMode::Client {
+                interpolation_delay,
+                ..
+            }) => InterpolationSettings {
+                delay: Duration::from_millis(interpolation_delay),
+                ..default()
+            },
+            _ => default(),
+        }
+    }
 }
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use bevy::{
+    prelude::*,
+    state::state::{FreelyMutableState, StateTransition},
+};
+
+#[cfg(feature = "net")]
+pub mod net;
+pub mod pong;
+pub mod snake;
+pub mod stick;
+
+/// Folder the games keep their files in: `bevy_fun` in the platform's user
+/// data directory, or `None` if that can't be found.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    data_dir.map(|dir| dir.join("bevy_fun"))
+}
+
+/// Applies a change of `S` requested by the previous fixed tick before the next
+/// one runs, so a game reacts on the same tick however many ticks fit in a
+/// frame. Bevy only applies state changes once per frame otherwise.
+///
+/// The whole [`StateTransition`] schedule runs, not just the part for `S`: its
+/// systems read transition events through their own cursors, so applying `S`
+/// from a schedule of our own would have the main one enter it a second time.
+/// It only runs while `S` has a change pending, which outside of fixed ticks
+/// the main schedule has already taken care of.
+pub(crate) fn apply_fixed_tick_transitions<S: FreelyMutableState>(world: &mut World) {
+    if matches!(world.resource::<NextState<S>>(), NextState::Pending(_)) {
+        // Added along with `S` by `init_state` or `add_sub_state`
+        world.run_schedule(StateTransition);
+    }
+}
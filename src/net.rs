@@ -0,0 +1,346 @@
+//! Two player pong over the network, packaged as a [`NetPongPlugin`].
+//!
+//! The server runs the whole [`PongPlugin`] simulation and replicates the
+//! ball, paddles, score and phase of the match with `bevy_replicon` over
+//! `bevy_quinnet`. Clients run it with [`PongConfig::simulate`] off, so they
+//! only draw what arrives, and send their paddle's input back as
+//! [`PaddleInput`] events. A client moves its own paddle ahead of the server,
+//! see [`prediction`], and shows everything else slightly in the past, see
+//! [`interpolation`].
+//!
+//! Everyone meets in a [`lobby`] first. The host of a server plays the left
+//! paddle and the first client to join the right one, and the match starts
+//! once they are all ready. A dedicated server has no host player, leaving
+//! both paddles to clients. Paddles nobody controls are played by the
+//! computer.
+//!
+//! Messages travel over QUIC with `bevy_quinnet`, or between apps in the
+//! same process over a [`loopback`], which is also how single player runs
+//! its match. Bad connections can be simulated on top of either, see
+//! [`conditions`], and what the connection is doing can be watched through
+//! [`diagnostics`].
+//!
+//! Before any of that, a [`protocol`] handshake turns away clients built
+//! from another version of the game, telling them why.
+
+mod client;
+pub mod conditions;
+pub mod diagnostics;
+pub mod interpolation;
+pub mod lobby;
+pub mod loopback;
+pub mod prediction;
+pub mod protocol;
+mod server;
+
+use std::{
+    net::{IpAddr, Ipv6Addr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_quinnet::RepliconQuinnetPlugins;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::{
+    Ball, GameMode, GamePhase, PongConfig, PongPlugin, Position, Scorer, Speed, Velocity,
+};
+
+pub use conditions::{
+    ChannelId, DelayLine, LinkConditions, NetworkConditions, NetworkConditionsPlugin,
+};
+pub use diagnostics::{
+    ChannelStats, ClientNetStats, NetStats, NetworkDiagnosticsPlugin, Ping, Pong,
+};
+pub use interpolation::{Interpolate, InterpolationPlugin, InterpolationSettings, Snapshots};
+pub use lobby::{
+    JoinLobby, LobbyList, LobbyPlayer, LocalPlayer, MatchStarted, NetState, PlayerInfo, SetReady,
+};
+pub use loopback::{Loopback, LoopbackClient, LoopbackServer, RepliconLoopbackPlugin};
+pub use prediction::{InputBudget, LastInput, PaddleAck, Prediction, step_paddle};
+pub use protocol::{Hello, PROTOCOL_VERSION, Protocol, ProtocolAppExt, Rejected, Welcome};
+
+/// Port servers listen on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 5000;
+
+/// How often dedicated servers log their [`NetStats`].
+pub const STATS_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Clients a server lets in unless told otherwise, the first two play and
+/// the rest watch.
+pub const DEFAULT_MAX_CLIENTS: usize = 8;
+
+/// Which end of a connection this app is, inserted as a resource by
+/// [`NetPongPlugin`].
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub enum NetRole {
+    /// Pong against the computer, hosted over a private [`Loopback`] so it
+    /// runs the same server code as a networked match, without the lobby
+    #[default]
+    SinglePlayer,
+    /// Hosts the match, and plays the left paddle unless it is dedicated
+    Server(ServerSettings),
+    /// Joins a server and plays whichever paddle it hands out
+    Client(ClientSettings),
+}
+
+/// Where and how a server listens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerSettings {
+    /// Address to listen on, [`Ipv6Addr::UNSPECIFIED`] for every interface
+    pub bind: IpAddr,
+    pub port: u16,
+    /// Clients connecting once this many are in are turned away
+    pub max_clients: usize,
+    pub certificate: ServerCertificate,
+    /// Nobody plays on the server itself, both paddles are left for clients
+    /// and the computer plays them until someone joins
+    pub dedicated: bool,
+    pub transport: Transport,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            bind: Ipv6Addr::LOCALHOST.into(),
+            port: DEFAULT_PORT,
+            max_clients: DEFAULT_MAX_CLIENTS,
+            certificate: default(),
+            dedicated: false,
+            transport: default(),
+        }
+    }
+}
+
+/// How messages travel between a server and its clients.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Transport {
+    /// QUIC over the network with `bevy_quinnet`
+    #[default]
+    Quinnet,
+    /// Between apps in the same process, sharing the [`Loopback`]
+    Loopback(Loopback),
+}
+
+/// TLS certificate a server presents to clients.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ServerCertificate {
+    /// A new one made up at startup for the address the server binds to.
+    /// Clients that pinned an earlier one refuse it
+    #[default]
+    SelfSigned,
+    /// PEM files, e.g. from a certificate authority
+    Files { cert: PathBuf, key: PathBuf },
+    /// PEM files made up and saved on first launch, then reused so the
+    /// server keeps the same identity
+    Persisted { cert: PathBuf, key: PathBuf },
+}
+
+impl ServerCertificate {
+    /// Persisted in [`data_dir`](crate::data_dir), self-signed every launch
+    /// when there is none.
+    pub fn in_data_dir() -> Self {
+        crate::data_dir().map_or(ServerCertificate::SelfSigned, |dir| {
+            ServerCertificate::Persisted {
+                cert: dir.join("server_cert.pem"),
+                key: dir.join("server_key.pem"),
+            }
+        })
+    }
+}
+
+/// Where a client connects and which servers it trusts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientSettings {
+    pub ip: IpAddr,
+    pub port: u16,
+    /// File remembering the certificate fingerprint of every server this
+    /// client trusted, `None` to only remember them until it quits.
+    /// [`default_known_hosts_file`] keeps them in the player's data folder
+    pub known_hosts: Option<PathBuf>,
+    /// The only fingerprint to trust for this server, replacing any known
+    /// one, as the server logs it when it starts
+    pub trust: Option<String>,
+    /// Connects to the server over this instead of the address above
+    pub transport: Transport,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            ip: Ipv6Addr::LOCALHOST.into(),
+            port: DEFAULT_PORT,
+            known_hosts: None,
+            trust: None,
+            transport: default(),
+        }
+    }
+}
+
+/// Where clients remember the servers they trust by default: in
+/// [`data_dir`](crate::data_dir), or `None` if that can't be found.
+pub fn default_known_hosts_file() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("known_hosts"))
+}
+
+/// Why the connection to the server was given up, shown to the player.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionError(pub String);
+
+#[derive(Default)]
+pub struct NetPongPlugin {
+    pub role: NetRole,
+    /// Match settings for the server and single player, clients draw whatever
+    /// match the server runs
+    pub pong: PongConfig,
+    /// How clients smooth out the ball and the other paddle
+    pub interpolation: InterpolationSettings,
+    /// Name and colour to join the lobby with
+    pub player: PlayerInfo,
+    /// Simulated latency, loss and such on this app's connections
+    pub conditions: NetworkConditions,
+}
+
+/// Sent by a client every frame with the input for its paddle.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PaddleInput {
+    /// Counts up from 0 with every input a client sends
+    pub sequence: u32,
+    /// Up is positive, in `-1.0..=1.0`
+    pub direction: f32,
+    /// Length of the frame the input was held for, in seconds
+    pub dt: f32,
+}
+
+/// Sent to a client when it is given a paddle to control.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssignedPaddle {
+    /// [`Paddle::player`] of the paddle
+    ///
+    /// [`Paddle::player`]: crate::pong::Paddle::player
+    pub player: u8,
+}
+
+/// Which player a replicated paddle belongs to. Clients build their own
+/// [`Paddle`] from it, so the input they read locally is never overwritten
+/// by the server's copy.
+///
+/// [`Paddle`]: crate::pong::Paddle
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaddleSlot(pub u8);
+
+/// Connected client controlling a paddle on the server, [`SERVER`] for the
+/// host's own paddle.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Deref)]
+pub struct PaddleOwner(pub Entity);
+
+/// The match score as replicated to clients, which copy it into their
+/// [`Score`](crate::pong::Score).
+#[derive(Component, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetScore {
+    pub player: u32,
+    pub ai: u32,
+}
+
+/// Where the match is as replicated to clients, which follow it into their
+/// own [`GamePhase`] and [`MatchWinner`](crate::pong::MatchWinner), e.g. to
+/// show the results screen.
+#[derive(Component, Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct NetPhase {
+    pub phase: GamePhase,
+    pub winner: Option<Scorer>,
+}
+
+/// Asks the server's transport to hang up on a client, after anything sent to
+/// it this frame.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisconnectRequest {
+    pub client_entity: Entity,
+}
+
+/// Paddle this client controls, once the server has handed one out.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalPaddle(pub u8);
+
+impl Plugin for NetPongPlugin {
+    fn build(&self, app: &mut App) {
+        let client = matches!(self.role, NetRole::Client(_));
+        let dedicated = matches!(&self.role, NetRole::Server(settings) if settings.dedicated);
+        app.add_plugins((
+            // Clients are only replicated to once their handshake went through
+            RepliconPlugins.set(ServerPlugin {
+                replicate_after_connect: false,
+                ..default()
+            }),
+            RepliconQuinnetPlugins,
+            RepliconLoopbackPlugin,
+            PongPlugin {
+                config: PongConfig {
+                    simulate: !client,
+                    // Both paddles wait for clients to take them over
+                    mode: if dedicated {
+                        GameMode::AiVsAi
+                    } else {
+                        self.pong.mode
+                    },
+                    ..self.pong.clone()
+                },
+            },
+        ));
+        let (role, state) = match &self.role {
+            // A server nobody else can join, already in the match
+            NetRole::SinglePlayer => (
+                NetRole::Server(ServerSettings {
+                    max_clients: 0,
+                    transport: Transport::Loopback(default()),
+                    ..default()
+                }),
+                NetState::InGame,
+            ),
+            role => (role.clone(), NetState::Lobby),
+        };
+        app.insert_resource(role.clone());
+        app.insert_resource(self.interpolation);
+        app.insert_resource(LocalPlayer(self.player.clone()));
+        app.insert_resource(self.conditions.clone());
+        app.add_plugins((
+            NetworkConditionsPlugin,
+            NetworkDiagnosticsPlugin {
+                // Nobody is there to look at the overlay
+                log_interval: dedicated.then_some(STATS_LOG_INTERVAL),
+            },
+        ));
+        protocol::register_handshake(app);
+        app.replicate_checked::<Ball>("ball", 1)
+            .replicate_checked::<PaddleSlot>("paddle slot", 1)
+            .replicate_checked::<Position>("position", 1)
+            .replicate_checked::<Speed>("speed", 1)
+            .replicate_checked::<Velocity>("velocity", 1)
+            .replicate_checked::<NetScore>("score", 1)
+            .replicate_checked::<NetPhase>("phase", 1)
+            .replicate_checked::<LobbyPlayer>("lobby player", 1)
+            .add_client_event_checked::<PaddleInput>("paddle input", 1, Channel::Unreliable)
+            .add_server_event_checked::<AssignedPaddle>("assigned paddle", 1, Channel::Ordered)
+            .add_server_event_checked::<PaddleAck>("paddle ack", 1, Channel::Unreliable)
+            .add_client_event_checked::<JoinLobby>("join lobby", 1, Channel::Ordered)
+            .add_client_event_checked::<SetReady>("set ready", 1, Channel::Ordered)
+            .add_server_event_checked::<MatchStarted>("match started", 1, Channel::Ordered)
+            .add_server_event_checked::<Ping>("ping", 1, Channel::Unreliable)
+            .add_client_event_checked::<Pong>("pong", 1, Channel::Unreliable);
+        // Round trips shouldn't include waiting for replication to catch up
+        app.make_independent::<Ping>();
+        match role {
+            NetRole::SinglePlayer => {}
+            NetRole::Server(_) => {
+                lobby::build(app, state);
+                server::build(app);
+            }
+            NetRole::Client(_) => {
+                lobby::build(app, state);
+                client::build(app);
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use std::{collections::HashMap as StdHashMap, fs, net::Ipv6Addr};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_quinnet::client::{
+    QuinnetClient,
+    certificate::{
+        CertInteractionEvent, CertVerificationStatus, CertVerifierAction, CertVerifierBehaviour,
+        CertificateVerificationMode, KnownHosts, TrustOnFirstUseConfig,
+    },
+    connection::ClientEndpointConfiguration,
+};
+use bevy_replicon::prelude::*;
+use bevy_replicon_quinnet::ChannelsConfigurationExt;
+
+use super::{
+    AssignedPaddle, ConnectionError, Hello, InterpolationPlugin, JoinLobby, LocalPaddle,
+    LocalPlayer, LoopbackClient, MatchStarted, NetPhase, NetRole, NetScore, NetState, PaddleAck,
+    PaddleInput, PaddleSlot, Prediction, Protocol, Rejected, Snapshots, Transport, Welcome,
+};
+use crate::pong::{
+    Ball, GameMode, GamePhase, InputBindings, MatchWinner, Paddle, Position, RED, Score, Speed,
+};
+
+pub(super) fn build(app: &mut App) {
+    app.add_plugins(InterpolationPlugin::<Position>::default());
+    app.add_systems(Startup, start_client);
+    app.add_observer(build_paddle);
+    app.add_observer(interpolate_ball);
+    app.add_systems(
+        PreUpdate,
+        (
+            (take_assigned_paddle, start_prediction, reconcile_paddle).chain(),
+            copy_score,
+            copy_phase,
+            start_match,
+        )
+            .after(ClientSet::Receive),
+    );
+    app.add_systems(
+        Update,
+        (
+            verify_certificate,
+            show_connection_error.run_if(resource_added::<ConnectionError>),
+            say_hello.run_if(client_just_connected),
+            join_lobby,
+            read_rejection,
+            predict_paddle.run_if(client_connected.and(in_state(NetState::InGame))),
+        ),
+    );
+}
+
+fn start_client(
+    mut commands: Commands,
+    mut client: ResMut<QuinnetClient>,
+    channels: Res<RepliconChannels>,
+    role: Res<NetRole>,
+) -> Result {
+    let NetRole::Client(settings) = &*role else {
+        return Ok(());
+    };
+    if let Transport::Loopback(loopback) = &settings.transport {
+        commands.insert_resource(loopback.connect());
+        return Ok(());
+    }
+    let known_hosts = match &settings.known_hosts {
+        Some(file) => {
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            KnownHosts::HostsFile(file.to_string_lossy().into_owned())
+        }
+        None => KnownHosts::Store(default()),
+    };
+    // Known servers go straight through unless a fingerprint was asked for,
+    // anything else is up to `verify_certificate`
+    let trusted = match settings.trust {
+        Some(_) => CertVerifierBehaviour::RequestClientAction,
+        None => CertVerifierBehaviour::ImmediateAction(CertVerifierAction::TrustOnce),
+    };
+    let verifier_behaviour = StdHashMap::from([
+        (CertVerificationStatus::TrustedCertificate, trusted),
+        (
+            CertVerificationStatus::UnknownCertificate,
+            CertVerifierBehaviour::RequestClientAction,
+        ),
+        (
+            CertVerificationStatus::UntrustedCertificate,
+            CertVerifierBehaviour::RequestClientAction,
+        ),
+    ]);
+    client.open_connection(
+        ClientEndpointConfiguration::from_ips(settings.ip, settings.port, Ipv6Addr::UNSPECIFIED, 0),
+        CertificateVerificationMode::TrustOnFirstUse(TrustOnFirstUseConfig {
+            known_hosts,
+            verifier_behaviour,
+        }),
+        channels.client_configs(),
+    )?;
+    info!("Connecting to {} port {}", settings.ip, settings.port);
+    Ok(())
+}
+
+/// Trusts servers on first use and remembers them, and refuses any whose
+/// certificate changed since, as someone may be pretending to be them.
+/// A fingerprint passed up front is the only one trusted.
+fn verify_certificate(
+    mut commands: Commands,
+    mut interactions: EventReader<CertInteractionEvent>,
+    role: Res<NetRole>,
+) -> Result {
+    let NetRole::Client(settings) = &*role else {
+        return Ok(());
+    };
+    for interaction in interactions.read() {
+        let fingerprint = interaction.info.fingerprint.to_base64();
+        let refusal = match (&settings.trust, &interaction.status) {
+            (Some(trust), _) if *trust == fingerprint => {
+                info!("Trusting the server with fingerprint {fingerprint} as asked");
+                None
+            }
+            (Some(trust), _) => Some(format!(
+                "The server didn't present the certificate you asked to trust.\n\n\
+                 Expected fingerprint {trust}\n\
+                 but it presented {fingerprint}"
+            )),
+            (None, CertVerificationStatus::UntrustedCertificate) => {
+                let known = interaction
+                    .info
+                    .known_fingerprint
+                    .as_ref()
+                    .map_or_else(|| "unknown".to_string(), |known| known.to_base64());
+                Some(format!(
+                    "The server's certificate changed since you last connected,\n\
+                     someone may be pretending to be it.\n\n\
+                     Expected fingerprint {known}\n\
+                     but it presented {fingerprint}\n\n\
+                     If you trust it, connect again with --trust {fingerprint}"
+                ))
+            }
+            (None, _) => {
+                info!("Trusting the server on first use, fingerprint {fingerprint}");
+                None
+            }
+        };
+        let action = match refusal {
+            Some(message) => {
+                error!("{message}");
+                commands.insert_resource(ConnectionError(message));
+                CertVerifierAction::AbortConnection
+            }
+            None => CertVerifierAction::TrustAndStore,
+        };
+        interaction.apply_cert_verifier_action(action)?;
+    }
+    Ok(())
+}
+
+fn show_connection_error(mut commands: Commands, error: Res<ConnectionError>) {
+    commands
+        .spawn((
+            Name::new("Connection Error"),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            GlobalZIndex(2),
+        ))
+        .with_child((
+            Text::new(error.0.clone()),
+            TextColor(RED),
+            TextLayout::new_with_justify(JustifyText::Center),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+        ));
+}
+
+fn say_hello(protocol: Res<Protocol>, mut hello: EventWriter<Hello>) {
+    hello.write(Hello(*protocol));
+}
+
+/// Asks for a place in the lobby once the server let this client in.
+fn join_lobby(
+    mut welcomes: EventReader<Welcome>,
+    local: Res<LocalPlayer>,
+    mut join: EventWriter<JoinLobby>,
+) {
+    if welcomes.read().count() > 0 {
+        join.write(JoinLobby(local.0.clone()));
+    }
+}
+
+/// Shows why the server turned this client away and hangs up.
+fn read_rejection(
+    mut commands: Commands,
+    mut rejections: EventReader<Rejected>,
+    mut client: ResMut<QuinnetClient>,
+    loopback: Option<Res<LoopbackClient>>,
+) -> Result {
+    let Some(Rejected(reason)) = rejections.read().last() else {
+        return Ok(());
+    };
+    error!("The server turned us away: {reason}");
+    commands.insert_resource(ConnectionError(reason.clone()));
+    match loopback {
+        Some(loopback) => loopback.disconnect(),
+        None => client.close_all_connections(),
+    }
+    Ok(())
+}
+
+fn start_match(
+    mut started: EventReader<MatchStarted>,
+    mut next_state: ResMut<NextState<NetState>>,
+) {
+    if started.read().count() > 0 {
+        next_state.set(NetState::InGame);
+    }
+}
+
+/// Turns a replicated paddle into one pong can read local input into. It is
+/// interpolated until it turns out to be the one this client predicts.
+fn build_paddle(
+    trigger: Trigger<OnAdd, PaddleSlot>,
+    mut commands: Commands,
+    slots: Query<&PaddleSlot>,
+) {
+    let Ok(slot) = slots.get(trigger.target()) else {
+        return;
+    };
+    commands.entity(trigger.target()).insert((
+        Paddle {
+            player: slot.0,
+            ..default()
+        },
+        Snapshots::<Position>::default(),
+    ));
+}
+
+fn interpolate_ball(trigger: Trigger<OnAdd, Ball>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert(Snapshots::<Position>::default());
+}
+
+/// Moves the keys player 1 would use over to whichever paddle the server
+/// handed out.
+fn take_assigned_paddle(
+    mut commands: Commands,
+    mut assigned: EventReader<AssignedPaddle>,
+    mut bindings: ResMut<InputBindings>,
+) {
+    let Some(AssignedPaddle { player }) = assigned.read().last() else {
+        return;
+    };
+    info!("Controlling paddle {player}");
+    let keys = InputBindings::for_mode(GameMode::VsAi)
+        .players
+        .remove(&1)
+        .unwrap_or_default();
+    bindings.players = HashMap::from_iter([(*player, keys)]);
+    commands.insert_resource(LocalPaddle(*player));
+}
+
+fn copy_score(net_score: Query<&NetScore, Changed<NetScore>>, mut score: ResMut<Score>) {
+    for net_score in &net_score {
+        score.player = net_score.player;
+        score.ai = net_score.ai;
+    }
+}
+
+/// Follows the server's match into the results screen and back out of it.
+fn copy_phase(
+    mut commands: Commands,
+    net_phase: Query<&NetPhase, Changed<NetPhase>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    for net_phase in &net_phase {
+        match net_phase.winner {
+            Some(winner) => commands.insert_resource(MatchWinner(winner)),
+            None => commands.remove_resource::<MatchWinner>(),
+        }
+        next_phase.set(net_phase.phase.clone());
+    }
+}
+
+/// Starts predicting the local paddle once the server has handed it out and
+/// replicated it, no longer interpolating it so the two don't fight over its
+/// position.
+fn start_prediction(
+    mut commands: Commands,
+    local: Option<Res<LocalPaddle>>,
+    paddles: Query<(Entity, &Paddle, &Position), Without<Prediction>>,
+) {
+    let Some(local) = local else {
+        return;
+    };
+    for (entity, _, position) in paddles
+        .iter()
+        .filter(|(_, paddle, _)| paddle.player == local.0)
+    {
+        commands
+            .entity(entity)
+            .insert(Prediction::new(position.0.y))
+            .remove::<Snapshots<Position>>();
+    }
+}
+
+/// Applies the newest ack, and puts the paddle back where it is predicted to
+/// be after replication moved it to the server's older position.
+fn reconcile_paddle(
+    mut acks: EventReader<PaddleAck>,
+    mut paddles: Query<(&mut Prediction, &Speed, &mut Position)>,
+) {
+    let ack = acks.read().max_by_key(|ack| ack.sequence);
+    for (mut prediction, speed, mut position) in &mut paddles {
+        if let Some(ack) = ack {
+            prediction.reconcile(ack, speed.0);
+        }
+        position.0.y = prediction.y();
+    }
+}
+
+/// Moves the local paddle with this frame's input right away and sends the
+/// input on to the server.
+fn predict_paddle(
+    time: Res<Time>,
+    mut paddles: Query<(&Paddle, &mut Prediction, &Speed, &mut Position)>,
+    mut inputs: EventWriter<PaddleInput>,
+) {
+    for (paddle, mut prediction, speed, mut position) in &mut paddles {
+        let input = prediction.predict(paddle.input_direction.0.y, time.delta_secs(), speed.0);
+        position.0.y = prediction.y();
+        inputs.write(input);
+    }
+}
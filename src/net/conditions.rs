@@ -0,0 +1,642 @@
+//! Simulated bad connections, for tuning netcode on one machine.
+//!
+//! [`NetworkConditionsPlugin`] sits between replicon and the transport. Every
+//! message this app sends or receives is held back, dropped, duplicated or
+//! shuffled as [`NetworkConditions`] says before it goes on its way, so
+//! running it on either end of a connection makes it bad both ways.
+//!
+//! Each [`Channel`] suffers the way it would over QUIC: messages on reliable
+//! channels are never lost, only delayed as if they had to be resent, ordered
+//! channels keep their order, and only unreliable messages turn up twice.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+    str::FromStr,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// Key toggling the panel.
+pub const PANEL_KEY: KeyCode = KeyCode::F2;
+
+/// Extra delay of a message picked to be reordered, long enough for the next
+/// couple of messages sent at 60 frames per second to overtake it.
+pub const REORDER_DELAY: Duration = Duration::from_millis(30);
+
+/// Most times a reliable message is resent before it is let through.
+const MAX_RESENDS: u32 = 8;
+
+const LATENCY_STEP: Duration = Duration::from_millis(10);
+const JITTER_STEP: Duration = Duration::from_millis(5);
+const CHANCE_STEP: f32 = 0.01;
+
+/// How bad a connection is, one way.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LinkConditions {
+    /// Time every message takes on its way
+    pub latency: Duration,
+    /// Most the latency of a message varies by, either way
+    pub jitter: Duration,
+    /// Chance a message is lost, in `0.0..=1.0`. Reliable channels resend it
+    /// instead
+    pub loss: f32,
+    /// Chance an unreliable message arrives twice
+    pub duplication: f32,
+    /// Chance a message is held back by [`REORDER_DELAY`] so later ones
+    /// overtake it, on channels that don't keep their order
+    pub reordering: f32,
+}
+
+impl LinkConditions {
+    pub fn is_perfect(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Time a message spends on its way, with jitter.
+    fn delay(&self, rng: &mut StdRng) -> Duration {
+        if self.jitter.is_zero() {
+            return self.latency;
+        }
+        let jitter = self.jitter.as_secs_f64();
+        let offset = rng.random_range(-jitter..=jitter);
+        Duration::from_secs_f64((self.latency.as_secs_f64() + offset).max(0.0))
+    }
+
+    /// Time until a lost reliable message is resent and gets there, about a
+    /// round trip.
+    fn resend_delay(&self, rng: &mut StdRng) -> Duration {
+        self.latency + self.delay(rng)
+    }
+}
+
+/// Parses the `latency=80,jitter=20,loss=5,duplication=1,reordering=2` form,
+/// in milliseconds and percent. Anything left out is perfect, and `none` or
+/// nothing at all is a perfect connection.
+impl FromStr for LinkConditions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut conditions = Self::default();
+        let s = s.trim();
+        if s.is_empty() || s == "none" {
+            return Ok(conditions);
+        }
+        for part in s.split(',') {
+            let Some((key, value)) = part.split_once('=') else {
+                return Err(format!("`{part}` should look like `latency=80`"));
+            };
+            let value: f64 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|value: &f64| value.is_finite() && *value >= 0.0)
+                .ok_or_else(|| format!("`{value}` isn't a positive number"))?;
+            let chance = || {
+                if value <= 100.0 {
+                    Ok((value / 100.0) as f32)
+                } else {
+                    Err(format!("`{key}` is a percentage, {value} is past 100"))
+                }
+            };
+            match key.trim() {
+                "latency" => conditions.latency = Duration::from_secs_f64(value / 1000.0),
+                "jitter" => conditions.jitter = Duration::from_secs_f64(value / 1000.0),
+                "loss" => conditions.loss = chance()?,
+                "duplication" => conditions.duplication = chance()?,
+                "reordering" => conditions.reordering = chance()?,
+                key => {
+                    return Err(format!(
+                        "unknown condition `{key}`, expected latency, jitter, loss, \
+                         duplication or reordering"
+                    ));
+                }
+            }
+        }
+        Ok(conditions)
+    }
+}
+
+impl fmt::Display for LinkConditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ms ± {} ms, {:.0}% lost, {:.0}% duplicated, {:.0}% reordered",
+            self.latency.as_millis(),
+            self.jitter.as_millis(),
+            self.loss * 100.0,
+            self.duplication * 100.0,
+            self.reordering * 100.0
+        )
+    }
+}
+
+/// A replicon channel. Channels are numbered separately for each way
+/// messages go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChannelId {
+    /// Sent by the server, e.g. replication and server events
+    ToClients(usize),
+    /// Sent by clients, e.g. client events
+    ToServer(usize),
+}
+
+/// Parses `to-clients/2` or `to-server/0`.
+impl FromStr for ChannelId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("`{s}` should look like `to-clients/2` or `to-server/0`");
+        let (way, id) = s.trim().split_once('/').ok_or_else(error)?;
+        let id = id.parse().map_err(|_| error())?;
+        match way {
+            "to-clients" => Ok(Self::ToClients(id)),
+            "to-server" => Ok(Self::ToServer(id)),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl fmt::Display for ChannelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ToClients(id) => write!(f, "to-clients/{id}"),
+            Self::ToServer(id) => write!(f, "to-server/{id}"),
+        }
+    }
+}
+
+/// How bad this app's connections are, inserted by
+/// [`NetPongPlugin`](super::NetPongPlugin) and changed live from the panel.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct NetworkConditions {
+    /// Conditions of every channel without its own
+    pub all: LinkConditions,
+    /// Conditions of single channels, in place of `all`
+    pub channels: BTreeMap<ChannelId, LinkConditions>,
+    /// Seed deciding what happens to each message, random when `None`
+    pub seed: Option<u64>,
+}
+
+impl NetworkConditions {
+    pub fn channel(&self, channel: ChannelId) -> LinkConditions {
+        self.channels.get(&channel).copied().unwrap_or(self.all)
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        self.all.is_perfect() && self.channels.values().all(LinkConditions::is_perfect)
+    }
+}
+
+struct Held<T> {
+    due: Duration,
+    target: T,
+    channel: usize,
+    message: Vec<u8>,
+}
+
+/// Messages held back on their way one way, for each `T` they are sent to or
+/// came from.
+pub struct DelayLine<T> {
+    rng: StdRng,
+    /// Sorted by when they are due, in the order they were pushed when due at
+    /// the same time
+    held: Vec<Held<T>>,
+    /// When the last message on each ordered channel is due, so the next
+    /// can't overtake it
+    last_ordered: HashMap<(T, usize), Duration>,
+}
+
+impl<T: Copy + Eq + Hash> DelayLine<T> {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_os_rng(),
+            },
+            held: Vec::new(),
+            last_ordered: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Holds `message` back until `conditions` let it through, if they do.
+    pub fn push(
+        &mut self,
+        now: Duration,
+        target: T,
+        channel: usize,
+        kind: Channel,
+        message: Vec<u8>,
+        conditions: &LinkConditions,
+    ) {
+        let rng = &mut self.rng;
+        let mut delay = conditions.delay(rng);
+        let mut copies = 1;
+        if matches!(kind, Channel::Unreliable) {
+            if rng.random::<f32>() < conditions.loss {
+                return;
+            }
+            if rng.random::<f32>() < conditions.duplication {
+                copies = 2;
+            }
+        } else {
+            for _ in 0..MAX_RESENDS {
+                if rng.random::<f32>() >= conditions.loss {
+                    break;
+                }
+                delay += conditions.resend_delay(rng);
+            }
+        }
+        if !matches!(kind, Channel::Ordered) && rng.random::<f32>() < conditions.reordering {
+            delay += REORDER_DELAY;
+        }
+
+        let mut due = now + delay;
+        if matches!(kind, Channel::Ordered) {
+            let last = self.last_ordered.entry((target, channel)).or_default();
+            due = due.max(*last);
+            *last = due;
+        }
+        for copy in 0..copies {
+            // The copy takes its own time on the way
+            let due = if copy == 0 {
+                due
+            } else {
+                now + conditions.delay(&mut self.rng)
+            };
+            let at = self.held.partition_point(|held| held.due <= due);
+            self.held.insert(
+                at,
+                Held {
+                    due,
+                    target,
+                    channel,
+                    message: message.clone(),
+                },
+            );
+        }
+    }
+
+    /// Takes every message due by `now`, in the order they arrive.
+    pub fn pop_due(&mut self, now: Duration) -> Vec<(T, usize, Vec<u8>)> {
+        let due = self.held.partition_point(|held| held.due <= now);
+        self.held
+            .drain(..due)
+            .map(|held| (held.target, held.channel, held.message))
+            .collect()
+    }
+
+    /// Whether anything sent to or from `target` is still held back.
+    pub fn holds(&self, target: T) -> bool {
+        self.held.iter().any(|held| held.target == target)
+    }
+
+    /// Forgets the order kept for a target that went away.
+    pub fn forget(&mut self, target: T) {
+        self.last_ordered.retain(|(held, _), _| *held != target);
+        self.held.retain(|held| held.target != target);
+    }
+}
+
+/// Every way messages are held back, on a server or a client.
+#[derive(Resource)]
+pub(super) struct DelayLines {
+    from_clients: DelayLine<Entity>,
+    to_clients: DelayLine<Entity>,
+    from_server: DelayLine<()>,
+    to_server: DelayLine<()>,
+}
+
+impl DelayLines {
+    /// Whether anything this server sent to `client` is still on its way.
+    pub(super) fn holds_for_client(&self, client: Entity) -> bool {
+        self.to_clients.holds(client)
+    }
+}
+
+/// Systems holding messages back, between replicon and the transport.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetworkConditionsSet;
+
+/// Runs messages through [`NetworkConditions`], and shows a panel to change
+/// them while playing.
+pub struct NetworkConditionsPlugin;
+
+impl Plugin for NetworkConditionsPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = app
+            .world_mut()
+            .get_resource_or_init::<NetworkConditions>()
+            .seed;
+        let line = |offset| seed.map(|seed: u64| seed.wrapping_add(offset));
+        app.insert_resource(DelayLines {
+            from_clients: DelayLine::new(line(0)),
+            to_clients: DelayLine::new(line(1)),
+            from_server: DelayLine::new(line(2)),
+            to_server: DelayLine::new(line(3)),
+        });
+        app.add_systems(
+            PreUpdate,
+            (
+                hold_from_clients
+                    .after(ServerSet::ReceivePackets)
+                    .before(ServerSet::Receive)
+                    .run_if(server_running),
+                hold_from_server
+                    .after(ClientSet::ReceivePackets)
+                    .before(ClientSet::Receive)
+                    .run_if(client_connected),
+            )
+                .in_set(NetworkConditionsSet),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                hold_to_clients
+                    .after(ServerSet::Send)
+                    .before(ServerSet::SendPackets)
+                    .run_if(server_running),
+                hold_to_server
+                    .after(ClientSet::Send)
+                    .before(ClientSet::SendPackets)
+                    .run_if(client_connected),
+            )
+                .in_set(NetworkConditionsSet),
+        );
+        app.add_observer(forget_client);
+        app.add_systems(Startup, spawn_panel);
+        app.add_systems(
+            Update,
+            (toggle_panel, select_row, adjust_conditions, update_panel).chain(),
+        );
+    }
+}
+
+fn hold_from_clients(
+    time: Res<Time<Real>>,
+    conditions: Res<NetworkConditions>,
+    channels: Res<RepliconChannels>,
+    mut lines: ResMut<DelayLines>,
+    mut server: ResMut<RepliconServer>,
+) {
+    let line = &mut lines.from_clients;
+    if conditions.is_perfect() && line.is_empty() {
+        return;
+    }
+    let now = time.elapsed();
+    for (channel, &kind) in channels.client_channels().iter().enumerate() {
+        let link = conditions.channel(ChannelId::ToServer(channel));
+        let received: Vec<_> = server.receive(channel).collect();
+        for (client, message) in received {
+            line.push(now, client, channel, kind, message.to_vec(), &link);
+        }
+    }
+    for (client, channel, message) in line.pop_due(now) {
+        server.insert_received(client, channel, message);
+    }
+}
+
+fn hold_to_clients(
+    time: Res<Time<Real>>,
+    conditions: Res<NetworkConditions>,
+    channels: Res<RepliconChannels>,
+    mut lines: ResMut<DelayLines>,
+    mut server: ResMut<RepliconServer>,
+) {
+    let line = &mut lines.to_clients;
+    if conditions.is_perfect() && line.is_empty() {
+        return;
+    }
+    let now = time.elapsed();
+    let sent: Vec<_> = server.drain_sent().collect();
+    for (client, channel, message) in sent {
+        let kind = channels.server_channels()[channel];
+        let link = conditions.channel(ChannelId::ToClients(channel));
+        line.push(now, client, channel, kind, message.to_vec(), &link);
+    }
+    for (client, channel, message) in line.pop_due(now) {
+        server.send(client, channel, message);
+    }
+}
+
+fn hold_from_server(
+    time: Res<Time<Real>>,
+    conditions: Res<NetworkConditions>,
+    channels: Res<RepliconChannels>,
+    mut lines: ResMut<DelayLines>,
+    mut client: ResMut<RepliconClient>,
+) {
+    let line = &mut lines.from_server;
+    if conditions.is_perfect() && line.is_empty() {
+        return;
+    }
+    let now = time.elapsed();
+    for (channel, &kind) in channels.server_channels().iter().enumerate() {
+        let link = conditions.channel(ChannelId::ToClients(channel));
+        let received: Vec<_> = client.receive(channel).collect();
+        for message in received {
+            line.push(now, (), channel, kind, message.to_vec(), &link);
+        }
+    }
+    for ((), channel, message) in line.pop_due(now) {
+        client.insert_received(channel, message);
+    }
+}
+
+fn hold_to_server(
+    time: Res<Time<Real>>,
+    conditions: Res<NetworkConditions>,
+    channels: Res<RepliconChannels>,
+    mut lines: ResMut<DelayLines>,
+    mut client: ResMut<RepliconClient>,
+) {
+    let line = &mut lines.to_server;
+    if conditions.is_perfect() && line.is_empty() {
+        return;
+    }
+    let now = time.elapsed();
+    let sent: Vec<_> = client.drain_sent().collect();
+    for (channel, message) in sent {
+        let kind = channels.client_channels()[channel];
+        let link = conditions.channel(ChannelId::ToServer(channel));
+        line.push(now, (), channel, kind, message.to_vec(), &link);
+    }
+    for ((), channel, message) in line.pop_due(now) {
+        client.send(channel, message);
+    }
+}
+
+/// Drops what was held for a client that left.
+fn forget_client(trigger: Trigger<OnRemove, ConnectedClient>, mut lines: ResMut<DelayLines>) {
+    lines.from_clients.forget(trigger.target());
+    lines.to_clients.forget(trigger.target());
+}
+
+/// Debug panel listing the conditions of every channel.
+#[derive(Component)]
+pub struct ConditionsPanel;
+
+/// Row of the panel the number keys change, `None` for every channel.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct SelectedRow(Option<ChannelId>);
+
+fn spawn_panel(mut commands: Commands) {
+    commands.init_resource::<SelectedRow>();
+    commands.spawn((
+        Name::new("Network Conditions"),
+        ConditionsPanel,
+        Text::default(),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        GlobalZIndex(3),
+        Visibility::Hidden,
+    ));
+}
+
+fn toggle_panel(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut panel: Single<&mut Visibility, With<ConditionsPanel>>,
+) {
+    if keys.just_pressed(PANEL_KEY) {
+        panel.toggle_visible_hidden();
+    }
+}
+
+/// Every row of the panel, the one for all channels first.
+fn rows(channels: &RepliconChannels) -> Vec<(Option<ChannelId>, Option<Channel>)> {
+    let to_clients = channels
+        .server_channels()
+        .iter()
+        .enumerate()
+        .map(|(id, &kind)| (Some(ChannelId::ToClients(id)), Some(kind)));
+    let to_server = channels
+        .client_channels()
+        .iter()
+        .enumerate()
+        .map(|(id, &kind)| (Some(ChannelId::ToServer(id)), Some(kind)));
+    [(None, None)]
+        .into_iter()
+        .chain(to_clients)
+        .chain(to_server)
+        .collect()
+}
+
+/// Tab moves to the next row while the panel is open.
+fn select_row(
+    keys: Res<ButtonInput<KeyCode>>,
+    channels: Res<RepliconChannels>,
+    panel: Single<&Visibility, With<ConditionsPanel>>,
+    mut selected: ResMut<SelectedRow>,
+) {
+    if **panel == Visibility::Hidden || !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let rows = rows(&channels);
+    let at = rows
+        .iter()
+        .position(|(row, _)| *row == selected.0)
+        .unwrap_or_default();
+    selected.0 = rows[(at + 1) % rows.len()].0;
+}
+
+/// The number keys make the selected row worse or better, in pairs: 1 and 2
+/// for latency, 3 and 4 for jitter, 5 and 6 for loss, 7 and 8 for
+/// duplication, 9 and 0 for reordering.
+fn adjust_conditions(
+    keys: Res<ButtonInput<KeyCode>>,
+    panel: Single<&Visibility, With<ConditionsPanel>>,
+    selected: Res<SelectedRow>,
+    mut conditions: ResMut<NetworkConditions>,
+) {
+    if **panel == Visibility::Hidden {
+        return;
+    }
+    let step = |down: KeyCode, up: KeyCode| {
+        (i32::from(keys.just_pressed(up)) - i32::from(keys.just_pressed(down))) as f32
+    };
+    let latency = step(KeyCode::Digit1, KeyCode::Digit2);
+    let jitter = step(KeyCode::Digit3, KeyCode::Digit4);
+    let loss = step(KeyCode::Digit5, KeyCode::Digit6);
+    let duplication = step(KeyCode::Digit7, KeyCode::Digit8);
+    let reordering = step(KeyCode::Digit9, KeyCode::Digit0);
+    if [latency, jitter, loss, duplication, reordering] == [0.0; 5] {
+        return;
+    }
+    let all = conditions.all;
+    let link = match selected.0 {
+        Some(channel) => conditions.channels.entry(channel).or_insert(all),
+        None => &mut conditions.all,
+    };
+    let nudge = |duration: Duration, step: Duration, by: f32| {
+        if by < 0.0 {
+            duration.saturating_sub(step)
+        } else if by > 0.0 {
+            duration + step
+        } else {
+            duration
+        }
+    };
+    link.latency = nudge(link.latency, LATENCY_STEP, latency);
+    link.jitter = nudge(link.jitter, JITTER_STEP, jitter);
+    for (chance, by) in [
+        (&mut link.loss, loss),
+        (&mut link.duplication, duplication),
+        (&mut link.reordering, reordering),
+    ] {
+        *chance = (*chance + CHANCE_STEP * by).clamp(0.0, 1.0);
+    }
+}
+
+fn update_panel(
+    conditions: Res<NetworkConditions>,
+    channels: Res<RepliconChannels>,
+    selected: Res<SelectedRow>,
+    panel: Single<(&mut Text, Ref<Visibility>), With<ConditionsPanel>>,
+) {
+    let (mut text, visibility) = panel.into_inner();
+    if *visibility == Visibility::Hidden
+        || !(conditions.is_changed() || selected.is_changed() || visibility.is_changed())
+    {
+        return;
+    }
+    let mut lines = vec!["Network conditions".to_string()];
+    for (row, kind) in rows(&channels) {
+        let marker = if row == selected.0 { ">" } else { " " };
+        let (name, link) = match (row, kind) {
+            (Some(channel), Some(kind)) => {
+                (format!("{channel} ({kind:?})"), conditions.channel(channel))
+            }
+            _ => ("every channel".to_string(), conditions.all),
+        };
+        lines.push(format!("{marker} {name}: {link}"));
+    }
+    lines.push(String::new());
+    lines.push(
+        "Tab: next row  1/2: latency  3/4: jitter  5/6: loss  7/8: duplication  \
+         9/0: reordering"
+            .to_string(),
+    );
+    text.0 = lines.join("\n");
+}
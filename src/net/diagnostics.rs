@@ -0,0 +1,596 @@
+//! What the connection is doing, for tuning netcode and spotting trouble.
+//!
+//! [`NetworkDiagnosticsPlugin`] counts every message as it goes over the
+//! transport, measures round trips with [`Ping`] and [`Pong`], and keeps a
+//! log of connection state changes. It all ends up in [`NetStats`], in a
+//! [`ClientNetStats`] on each client entity of a server, and in Bevy
+//! diagnostics under `net/` so it can be recorded. F3 shows it in an overlay,
+//! and headless servers log a summary every so often instead.
+
+use std::{collections::BTreeMap, collections::VecDeque, time::Duration};
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore, RegisterDiagnostic,
+    },
+    platform::time::Instant,
+    prelude::*,
+    state::state::StateTransitionEvent,
+};
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{ChannelId, NetState, conditions::NetworkConditionsSet};
+
+/// Key toggling the overlay.
+pub const OVERLAY_KEY: KeyCode = KeyCode::F3;
+
+/// How often servers ping each client.
+pub const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// State changes kept for the overlay.
+const MAX_EVENTS: usize = 6;
+
+/// Pings a server keeps waiting for an answer to, per client.
+const MAX_PENDING_PINGS: usize = 8;
+
+/// Sent by the server to each client every [`PING_INTERVAL`], answered right
+/// away with a [`Pong`].
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Ping {
+    pub sequence: u32,
+    /// Round trip the server last measured to this client, in seconds
+    pub rtt: Option<f32>,
+}
+
+/// A client's answer to a [`Ping`].
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pong {
+    pub sequence: u32,
+}
+
+/// Messages and bytes that went over one channel since the app started.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+    pub messages: u64,
+    pub bytes: u64,
+    /// Messages over the last second
+    pub messages_per_second: f64,
+    last_messages: u64,
+}
+
+/// Traffic of this app as a whole, inserted by [`NetworkDiagnosticsPlugin`].
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct NetStats {
+    /// Round trip to the server on a client, or the mean over every client on
+    /// a server
+    pub rtt: Option<Duration>,
+    /// Over the last second
+    pub bytes_sent_per_second: f64,
+    pub bytes_received_per_second: f64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub replicated_entities: usize,
+    pub channels: BTreeMap<ChannelId, ChannelStats>,
+    /// Latest connection state changes, oldest first
+    pub events: VecDeque<String>,
+    last_sent: u64,
+    last_received: u64,
+}
+
+impl NetStats {
+    fn count(&mut self, channel: ChannelId, bytes: usize, sent: bool) {
+        let bytes = bytes as u64;
+        let stats = self.channels.entry(channel).or_default();
+        stats.messages += 1;
+        stats.bytes += bytes;
+        if sent {
+            self.bytes_sent += bytes;
+        } else {
+            self.bytes_received += bytes;
+        }
+    }
+
+    /// Logs `event` and keeps it for the overlay.
+    fn record(&mut self, event: String) {
+        info!("{event}");
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Traffic of one client, on the server's entity for it.
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct ClientNetStats {
+    pub rtt: Option<Duration>,
+    /// Over the last second
+    pub bytes_sent_per_second: f64,
+    pub bytes_received_per_second: f64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    last_sent: u64,
+    last_received: u64,
+    next_ping: u32,
+    /// Pings sent and not answered yet, with when they were sent
+    pending: VecDeque<(u32, Duration)>,
+}
+
+/// Counts traffic and measures round trips, see the [module docs](self).
+pub struct NetworkDiagnosticsPlugin {
+    /// How often to log a summary, e.g. on a headless server
+    pub log_interval: Option<Duration>,
+}
+
+impl NetworkDiagnosticsPlugin {
+    pub const RTT: DiagnosticPath = DiagnosticPath::const_new("net/rtt");
+    pub const BYTES_SENT: DiagnosticPath = DiagnosticPath::const_new("net/bytes_sent");
+    pub const BYTES_RECEIVED: DiagnosticPath = DiagnosticPath::const_new("net/bytes_received");
+    pub const REPLICATED_ENTITIES: DiagnosticPath =
+        DiagnosticPath::const_new("net/replicated_entities");
+
+    /// Messages per second over `channel`.
+    pub fn channel_messages(channel: ChannelId) -> DiagnosticPath {
+        DiagnosticPath::new(format!("net/channels/{channel}/messages"))
+    }
+
+    /// Round trip to one client of a server.
+    pub fn client_rtt(client: Entity) -> DiagnosticPath {
+        DiagnosticPath::new(format!("net/clients/{client}/rtt"))
+    }
+}
+
+/// Times the stats are rolled over and logged at.
+#[derive(Resource)]
+struct StatsTimers {
+    second: Timer,
+    log: Option<Timer>,
+    ping: Timer,
+}
+
+impl Plugin for NetworkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetStats>();
+        app.insert_resource(StatsTimers {
+            second: Timer::from_seconds(1.0, TimerMode::Repeating),
+            log: self
+                .log_interval
+                .map(|interval| Timer::new(interval, TimerMode::Repeating)),
+            ping: Timer::new(PING_INTERVAL, TimerMode::Repeating),
+        });
+        app.register_diagnostic(Diagnostic::new(Self::RTT).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(Self::BYTES_SENT).with_suffix(" B/s"))
+            .register_diagnostic(Diagnostic::new(Self::BYTES_RECEIVED).with_suffix(" B/s"))
+            .register_diagnostic(Diagnostic::new(Self::REPLICATED_ENTITIES));
+        app.add_observer(track_client).add_observer(untrack_client);
+        app.add_systems(
+            PreUpdate,
+            (
+                count_from_clients
+                    .after(ServerSet::ReceivePackets)
+                    .after(NetworkConditionsSet)
+                    .before(ServerSet::Receive)
+                    .run_if(server_running),
+                count_from_server
+                    .after(ClientSet::ReceivePackets)
+                    .after(NetworkConditionsSet)
+                    .before(ClientSet::Receive)
+                    .run_if(client_connected),
+                read_pongs.after(ServerSet::Receive).run_if(server_running),
+                answer_pings
+                    .after(ClientSet::Receive)
+                    .run_if(client_connected),
+            ),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                count_to_clients
+                    .after(ServerSet::Send)
+                    .after(NetworkConditionsSet)
+                    .before(ServerSet::SendPackets)
+                    .run_if(server_running),
+                count_to_server
+                    .after(ClientSet::Send)
+                    .after(NetworkConditionsSet)
+                    .before(ClientSet::SendPackets)
+                    .run_if(client_connected),
+            ),
+        );
+        app.add_systems(Startup, spawn_overlay);
+        app.add_systems(
+            Update,
+            (
+                send_pings.run_if(server_running),
+                record_transitions,
+                (roll_stats, log_stats).chain(),
+                (toggle_overlay, update_overlay).chain(),
+            ),
+        );
+    }
+}
+
+fn count_from_clients(
+    channels: Res<RepliconChannels>,
+    mut stats: ResMut<NetStats>,
+    mut server: ResMut<RepliconServer>,
+    mut clients: Query<&mut ClientNetStats>,
+) {
+    for channel in 0..channels.client_channels().len() {
+        let received: Vec<_> = server.receive(channel).collect();
+        for (client, message) in received {
+            stats.count(ChannelId::ToServer(channel), message.len(), false);
+            if let Ok(mut client) = clients.get_mut(client) {
+                client.bytes_received += message.len() as u64;
+            }
+            server.insert_received(client, channel, message);
+        }
+    }
+}
+
+fn count_to_clients(
+    mut stats: ResMut<NetStats>,
+    mut server: ResMut<RepliconServer>,
+    mut clients: Query<&mut ClientNetStats>,
+) {
+    let sent: Vec<_> = server.drain_sent().collect();
+    for (client, channel, message) in sent {
+        stats.count(ChannelId::ToClients(channel), message.len(), true);
+        if let Ok(mut client) = clients.get_mut(client) {
+            client.bytes_sent += message.len() as u64;
+        }
+        server.send(client, channel, message);
+    }
+}
+
+fn count_from_server(
+    channels: Res<RepliconChannels>,
+    mut stats: ResMut<NetStats>,
+    mut client: ResMut<RepliconClient>,
+) {
+    for channel in 0..channels.server_channels().len() {
+        let received: Vec<_> = client.receive(channel).collect();
+        for message in received {
+            stats.count(ChannelId::ToClients(channel), message.len(), false);
+            client.insert_received(channel, message);
+        }
+    }
+}
+
+fn count_to_server(mut stats: ResMut<NetStats>, mut client: ResMut<RepliconClient>) {
+    let sent: Vec<_> = client.drain_sent().collect();
+    for (channel, message) in sent {
+        stats.count(ChannelId::ToServer(channel), message.len(), true);
+        client.send(channel, message);
+    }
+}
+
+fn track_client(
+    trigger: Trigger<OnAdd, ConnectedClient>,
+    mut commands: Commands,
+    mut stats: ResMut<NetStats>,
+) {
+    commands
+        .entity(trigger.target())
+        .insert(ClientNetStats::default());
+    stats.record(format!("Client `{}` connected", trigger.target()));
+}
+
+fn untrack_client(
+    trigger: Trigger<OnRemove, ConnectedClient>,
+    mut stats: ResMut<NetStats>,
+    mut store: ResMut<DiagnosticsStore>,
+) {
+    if let Some(rtt) = store.get_mut(&NetworkDiagnosticsPlugin::client_rtt(trigger.target())) {
+        rtt.is_enabled = false;
+    }
+    stats.record(format!("Client `{}` disconnected", trigger.target()));
+}
+
+/// Logs the server starting and stopping, clients connecting to a server
+/// and the session moving between the lobby and the match.
+fn record_transitions(
+    mut stats: ResMut<NetStats>,
+    server: Res<RepliconServer>,
+    client: Res<RepliconClient>,
+    mut was_running: Local<bool>,
+    mut was_status: Local<Option<RepliconClientStatus>>,
+    mut transitions: EventReader<StateTransitionEvent<NetState>>,
+) {
+    if server.is_running() != *was_running {
+        *was_running = server.is_running();
+        stats.record(if *was_running {
+            "Server started".to_string()
+        } else {
+            "Server stopped".to_string()
+        });
+    }
+    let status = client.status();
+    if *was_status != Some(status) {
+        // Clients start out disconnected, which isn't worth mentioning
+        if was_status.is_some() || status != RepliconClientStatus::Disconnected {
+            stats.record(format!("Client {status:?}"));
+        }
+        *was_status = Some(status);
+    }
+    for transition in transitions.read() {
+        if let (Some(exited), Some(entered)) = (transition.exited, transition.entered)
+            && exited != entered
+        {
+            stats.record(format!("{exited:?} -> {entered:?}"));
+        }
+    }
+}
+
+fn send_pings(
+    time: Res<Time<Real>>,
+    mut timers: ResMut<StatsTimers>,
+    mut clients: Query<(Entity, &mut ClientNetStats), With<ReplicatedClient>>,
+    mut pings: EventWriter<ToClients<Ping>>,
+) {
+    if !timers.ping.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (client, mut stats) in &mut clients {
+        let sequence = stats.next_ping;
+        stats.next_ping = sequence.wrapping_add(1);
+        if stats.pending.len() == MAX_PENDING_PINGS {
+            stats.pending.pop_front();
+        }
+        stats.pending.push_back((sequence, time.elapsed()));
+        pings.write(ToClients {
+            mode: SendMode::Direct(client),
+            event: Ping {
+                sequence,
+                rtt: stats.rtt.map(|rtt| rtt.as_secs_f32()),
+            },
+        });
+    }
+}
+
+fn read_pongs(
+    time: Res<Time<Real>>,
+    mut pongs: EventReader<FromClient<Pong>>,
+    mut clients: Query<&mut ClientNetStats>,
+) {
+    for FromClient {
+        client_entity,
+        event,
+    } in pongs.read()
+    {
+        let Ok(mut stats) = clients.get_mut(*client_entity) else {
+            continue;
+        };
+        let Some(at) = stats
+            .pending
+            .iter()
+            .position(|(sequence, _)| *sequence == event.sequence)
+        else {
+            continue;
+        };
+        let (_, sent) = stats.pending[at];
+        // Older pings still waiting were lost
+        stats.pending.drain(..=at);
+        stats.rtt = Some(time.elapsed().saturating_sub(sent));
+    }
+}
+
+fn answer_pings(
+    mut pings: EventReader<Ping>,
+    mut stats: ResMut<NetStats>,
+    mut pongs: EventWriter<Pong>,
+) {
+    for ping in pings.read() {
+        pongs.write(Pong {
+            sequence: ping.sequence,
+        });
+        if let Some(rtt) = ping.rtt {
+            stats.rtt = Some(Duration::from_secs_f32(rtt));
+        }
+    }
+}
+
+/// Works out the rates over the last second and hands everything to Bevy's
+/// diagnostics.
+fn roll_stats(
+    time: Res<Time<Real>>,
+    mut timers: ResMut<StatsTimers>,
+    mut stats: ResMut<NetStats>,
+    mut clients: Query<(Entity, &mut ClientNetStats)>,
+    replicated: Query<(), With<Replicated>>,
+    mut store: ResMut<DiagnosticsStore>,
+) {
+    if !timers.second.tick(time.delta()).just_finished() {
+        return;
+    }
+    let seconds = timers.second.duration().as_secs_f64();
+    let stats = &mut *stats;
+    stats.bytes_sent_per_second = (stats.bytes_sent - stats.last_sent) as f64 / seconds;
+    stats.bytes_received_per_second = (stats.bytes_received - stats.last_received) as f64 / seconds;
+    stats.last_sent = stats.bytes_sent;
+    stats.last_received = stats.bytes_received;
+    stats.replicated_entities = replicated.iter().count();
+    for (&channel, channel_stats) in &mut stats.channels {
+        channel_stats.messages_per_second =
+            (channel_stats.messages - channel_stats.last_messages) as f64 / seconds;
+        channel_stats.last_messages = channel_stats.messages;
+        measure(
+            &mut store,
+            NetworkDiagnosticsPlugin::channel_messages(channel),
+            "",
+            channel_stats.messages_per_second,
+        );
+    }
+
+    let mut rtts = Vec::new();
+    for (client, mut client_stats) in &mut clients {
+        client_stats.bytes_sent_per_second =
+            (client_stats.bytes_sent - client_stats.last_sent) as f64 / seconds;
+        client_stats.bytes_received_per_second =
+            (client_stats.bytes_received - client_stats.last_received) as f64 / seconds;
+        client_stats.last_sent = client_stats.bytes_sent;
+        client_stats.last_received = client_stats.bytes_received;
+        if let Some(rtt) = client_stats.rtt {
+            rtts.push(rtt);
+            measure(
+                &mut store,
+                NetworkDiagnosticsPlugin::client_rtt(client),
+                " ms",
+                rtt.as_secs_f64() * 1000.0,
+            );
+        }
+    }
+    if !rtts.is_empty() {
+        stats.rtt = Some(rtts.iter().sum::<Duration>() / rtts.len() as u32);
+    }
+
+    if let Some(rtt) = stats.rtt {
+        measure(
+            &mut store,
+            NetworkDiagnosticsPlugin::RTT,
+            " ms",
+            rtt.as_secs_f64() * 1000.0,
+        );
+    }
+    for (path, value) in [
+        (
+            NetworkDiagnosticsPlugin::BYTES_SENT,
+            stats.bytes_sent_per_second,
+        ),
+        (
+            NetworkDiagnosticsPlugin::BYTES_RECEIVED,
+            stats.bytes_received_per_second,
+        ),
+        (
+            NetworkDiagnosticsPlugin::REPLICATED_ENTITIES,
+            stats.replicated_entities as f64,
+        ),
+    ] {
+        measure(&mut store, path, "", value);
+    }
+}
+
+/// Adds a measurement to the diagnostic at `path`, adding the diagnostic
+/// first if it is new, e.g. for a client that just connected.
+fn measure(store: &mut DiagnosticsStore, path: DiagnosticPath, suffix: &'static str, value: f64) {
+    if store.get(&path).is_none() {
+        store.add(Diagnostic::new(path.clone()).with_suffix(suffix));
+    }
+    if let Some(diagnostic) = store.get_mut(&path)
+        && diagnostic.is_enabled
+    {
+        diagnostic.add_measurement(DiagnosticMeasurement {
+            time: Instant::now(),
+            value,
+        });
+    }
+}
+
+fn log_stats(
+    time: Res<Time<Real>>,
+    mut timers: ResMut<StatsTimers>,
+    stats: Res<NetStats>,
+    clients: Query<(Entity, &ClientNetStats)>,
+) {
+    let Some(log) = &mut timers.log else {
+        return;
+    };
+    if !log.tick(time.delta()).just_finished() {
+        return;
+    }
+    info!("{}", summary(&stats, &clients));
+}
+
+/// Everything in [`NetStats`] and each [`ClientNetStats`], one line each.
+fn summary(stats: &NetStats, clients: &Query<(Entity, &ClientNetStats)>) -> String {
+    let mut lines = vec![format!(
+        "RTT {}, sent {}, received {}, {} replicated entities",
+        rtt(stats.rtt),
+        rate(stats.bytes_sent_per_second),
+        rate(stats.bytes_received_per_second),
+        stats.replicated_entities
+    )];
+    for (client, client_stats) in clients {
+        lines.push(format!(
+            "Client `{client}`: RTT {}, sent {}, received {}",
+            rtt(client_stats.rtt),
+            rate(client_stats.bytes_sent_per_second),
+            rate(client_stats.bytes_received_per_second)
+        ));
+    }
+    for (channel, channel_stats) in &stats.channels {
+        lines.push(format!(
+            "Channel {channel}: {} messages, {:.0}/s, {} bytes",
+            channel_stats.messages, channel_stats.messages_per_second, channel_stats.bytes
+        ));
+    }
+    lines.join("\n")
+}
+
+fn rtt(rtt: Option<Duration>) -> String {
+    rtt.map_or_else(
+        || "unknown".to_string(),
+        |rtt| format!("{} ms", rtt.as_millis()),
+    )
+}
+
+fn rate(bytes_per_second: f64) -> String {
+    if bytes_per_second >= 1024.0 {
+        format!("{:.1} KiB/s", bytes_per_second / 1024.0)
+    } else {
+        format!("{bytes_per_second:.0} B/s")
+    }
+}
+
+/// Overlay showing [`NetStats`].
+#[derive(Component)]
+pub struct DiagnosticsOverlay;
+
+fn spawn_overlay(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Network Diagnostics"),
+        DiagnosticsOverlay,
+        Text::default(),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        GlobalZIndex(3),
+        Visibility::Hidden,
+    ));
+}
+
+fn toggle_overlay(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut overlay: Single<&mut Visibility, With<DiagnosticsOverlay>>,
+) {
+    if keys.just_pressed(OVERLAY_KEY) {
+        overlay.toggle_visible_hidden();
+    }
+}
+
+fn update_overlay(
+    stats: Res<NetStats>,
+    clients: Query<(Entity, &ClientNetStats)>,
+    overlay: Single<(&mut Text, Ref<Visibility>), With<DiagnosticsOverlay>>,
+) {
+    let (mut text, visibility) = overlay.into_inner();
+    if *visibility == Visibility::Hidden || !(stats.is_changed() || visibility.is_changed()) {
+        return;
+    }
+    let mut lines = vec!["Network diagnostics".to_string(), summary(&stats, &clients)];
+    if !stats.events.is_empty() {
+        lines.push(String::new());
+        lines.extend(stats.events.iter().cloned());
+    }
+    text.0 = lines.join("\n");
+}
@@ -0,0 +1,185 @@
+//! Smooths out replicated values that only arrive at the network tick rate.
+//!
+//! Every value received for a component with [`Snapshots`] is stamped with
+//! the time it arrived and kept for a while. The component itself is then
+//! shown [`InterpolationSettings::delay`] in the past, between the two
+//! snapshots either side of that moment, so it moves smoothly however
+//! unevenly the packets came in.
+//!
+//! When packets are late and the buffer runs dry the value is extrapolated
+//! along its last movement, for at most
+//! [`InterpolationSettings::max_extrapolation`]. After that it sits on the
+//! last value received: the server touches every position it replicates each
+//! tick, so a longer gap means the value simply stopped changing.
+
+use std::{collections::VecDeque, marker::PhantomData, time::Duration};
+
+use bevy::{ecs::component::Mutable, prelude::*};
+use bevy_replicon::prelude::*;
+
+use crate::pong::{Position, Velocity};
+
+/// Snapshots kept per entity, a couple of seconds' worth at 60 ticks per
+/// second. Older ones are dropped as soon as they are no longer needed.
+const MAX_SNAPSHOTS: usize = 128;
+
+/// A replicated value that can be blended between two snapshots.
+pub trait Interpolate: Component<Mutability = Mutable> + Clone {
+    /// Value `t` of the way from `self` to `to`. `t` goes past `1.0` when
+    /// extrapolating.
+    fn interpolate(&self, to: &Self, t: f32) -> Self;
+}
+
+/// Jumps further than this, like the ball being served again from the
+/// center, snap straight to the new position instead of sliding there.
+pub const TELEPORT_DISTANCE: f32 = 20.0;
+
+impl Interpolate for Position {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        if self.0.distance(to.0) > TELEPORT_DISTANCE {
+            return if t > 0.0 { *to } else { *self };
+        }
+        Position(self.0.lerp(to.0, t))
+    }
+}
+
+impl Interpolate for Velocity {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        Velocity(self.0.lerp(to.0, t.min(1.0)))
+    }
+}
+
+/// How far in the past interpolated components are shown.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct InterpolationSettings {
+    /// Time in the past to show values at, a few network ticks is enough to
+    /// always have a snapshot either side
+    pub delay: Duration,
+    /// Longest a value is carried on past the last snapshot
+    pub max_extrapolation: Duration,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(100),
+            max_extrapolation: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Timestamped values of `C` received for an entity. Insert it on a
+/// replicated entity to have its `C` interpolated.
+#[derive(Component, Debug, Clone)]
+pub struct Snapshots<C: Interpolate> {
+    buffer: VecDeque<(Duration, C)>,
+}
+
+impl<C: Interpolate> Default for Snapshots<C> {
+    fn default() -> Self {
+        Self {
+            buffer: VecDeque::new(),
+        }
+    }
+}
+
+impl<C: Interpolate> Snapshots<C> {
+    /// Records `value` as received at `time`. Values received in the same
+    /// frame replace each other.
+    pub fn push(&mut self, time: Duration, value: C) {
+        if let Some((last, _)) = self.buffer.back()
+            && *last >= time
+        {
+            self.buffer.pop_back();
+        }
+        if self.buffer.len() == MAX_SNAPSHOTS {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back((time, value));
+    }
+
+    /// Drops snapshots that are no use for showing `time` or later.
+    pub fn prune(&mut self, time: Duration) {
+        while self.buffer.get(1).is_some_and(|(next, _)| *next <= time) {
+            self.buffer.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Value at `time`, extrapolated for at most `max_extrapolation` past the
+    /// last snapshot. Before the first snapshot it is the first snapshot.
+    pub fn sample(&self, time: Duration, max_extrapolation: Duration) -> Option<C> {
+        let next = self.buffer.iter().position(|(at, _)| *at > time);
+        let (from, to) = match next {
+            Some(0) => return self.buffer.front().map(|(_, value)| value.clone()),
+            Some(next) => (&self.buffer[next - 1], &self.buffer[next]),
+            None => {
+                let len = self.buffer.len();
+                if len < 2 || time - self.buffer[len - 1].0 > max_extrapolation {
+                    return self.buffer.back().map(|(_, value)| value.clone());
+                }
+                (&self.buffer[len - 2], &self.buffer[len - 1])
+            }
+        };
+        let t = (time - from.0).as_secs_f32() / (to.0 - from.0).as_secs_f32();
+        Some(from.1.interpolate(&to.1, t))
+    }
+}
+
+/// Interpolates `C` on client entities that have [`Snapshots<C>`].
+pub struct InterpolationPlugin<C>(PhantomData<C>);
+
+impl<C> Default for InterpolationPlugin<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C: Interpolate> Plugin for InterpolationPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InterpolationSettings>();
+        app.add_systems(
+            PreUpdate,
+            (record_snapshots::<C>, interpolate::<C>)
+                .chain()
+                .after(ClientSet::Receive),
+        );
+    }
+}
+
+/// Stores every value replication wrote since the last frame.
+fn record_snapshots<C: Interpolate>(
+    time: Res<Time<Real>>,
+    mut query: Query<(Ref<C>, &mut Snapshots<C>)>,
+) {
+    for (value, mut snapshots) in &mut query {
+        if value.is_changed() {
+            snapshots.push(time.elapsed(), value.clone());
+        }
+    }
+}
+
+/// Shows every value as it was [`InterpolationSettings::delay`] ago. The
+/// write skips change detection so it isn't mistaken for a new snapshot.
+fn interpolate<C: Interpolate>(
+    time: Res<Time<Real>>,
+    settings: Res<InterpolationSettings>,
+    mut query: Query<(&mut C, &mut Snapshots<C>)>,
+) {
+    let Some(render_time) = time.elapsed().checked_sub(settings.delay) else {
+        return;
+    };
+    for (mut value, mut snapshots) in &mut query {
+        snapshots.prune(render_time);
+        if let Some(sampled) = snapshots.sample(render_time, settings.max_extrapolation) {
+            *value.bypass_change_detection() = sampled;
+        }
+    }
+}
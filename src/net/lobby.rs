@@ -0,0 +1,224 @@
+//! Waiting room before a networked match.
+//!
+//! Everyone joining sends a [`JoinLobby`] with their name and colour, and the
+//! server hands them a paddle if one is free, or lets them watch. The match is
+//! held until every player has sent [`SetReady`], then the server moves to
+//! [`NetState::InGame`] and tells the clients with [`MatchStarted`].
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::LocalPaddle;
+use crate::pong::{Paddle, TEXT};
+
+const OVERLAY: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+/// Longest name players are shown with, anything past it is cut off.
+pub const MAX_NAME_LEN: usize = 16;
+
+/// Where a networked session is at.
+#[derive(States, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum NetState {
+    /// Waiting for everyone to ready up, the match is held
+    #[default]
+    Lobby,
+    InGame,
+}
+
+/// How a player wants to be shown to everyone else.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlayerInfo {
+    pub name: String,
+    /// sRGB colour of their paddle and name
+    pub color: [u8; 3],
+}
+
+impl Default for PlayerInfo {
+    fn default() -> Self {
+        Self {
+            name: "Player".to_string(),
+            color: [137, 180, 250],
+        }
+    }
+}
+
+impl PlayerInfo {
+    pub fn color(&self) -> Color {
+        let [r, g, b] = self.color;
+        Color::srgb_u8(r, g, b)
+    }
+
+    /// Trims the name and cuts it down to [`MAX_NAME_LEN`], falling back to
+    /// `fallback` when nothing is left.
+    pub fn sanitized(&self, fallback: impl FnOnce() -> String) -> Self {
+        let name: String = self
+            .name
+            .trim()
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_NAME_LEN)
+            .collect();
+        Self {
+            name: if name.is_empty() { fallback() } else { name },
+            color: self.color,
+        }
+    }
+}
+
+/// Sent by a client once it is connected, asking for a place in the lobby.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JoinLobby(pub PlayerInfo);
+
+/// Sent by a player to say whether they are ready to start.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetReady(pub bool);
+
+/// Sent to clients when everyone is ready, or straight away to a client
+/// joining a match already under way.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchStarted;
+
+/// Someone in the lobby, replicated to every client.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LobbyPlayer {
+    pub info: PlayerInfo,
+    /// [`Paddle::player`] of the paddle they play, `None` when watching
+    pub slot: Option<u8>,
+    pub ready: bool,
+}
+
+/// Column the lobby screen lists players in.
+#[derive(Component)]
+pub struct LobbyList;
+
+/// The name and colour this app joins with, inserted by
+/// [`NetPongPlugin`](super::NetPongPlugin).
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Deref)]
+pub struct LocalPlayer(pub PlayerInfo);
+
+pub(super) fn build(app: &mut App, state: NetState) {
+    app.insert_state(state);
+    app.enable_state_scoped_entities::<NetState>();
+    app.add_systems(OnEnter(NetState::Lobby), spawn_lobby_screen);
+    app.add_systems(
+        Update,
+        (update_lobby_list, toggle_ready).run_if(in_state(NetState::Lobby)),
+    );
+    app.add_systems(Update, tint_paddles);
+}
+
+fn spawn_lobby_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Lobby"),
+            StateScoped(NetState::Lobby),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(OVERLAY),
+            GlobalZIndex(1),
+        ))
+        .with_children(|lobby| {
+            lobby.spawn((
+                Text::new("Lobby"),
+                TextColor(TEXT),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+            ));
+            lobby.spawn((
+                LobbyList,
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+            ));
+            lobby.spawn((
+                Text::new("Press Enter when you're ready"),
+                TextColor(TEXT),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn status(player: &LobbyPlayer) -> &'static str {
+    match (player.slot, player.ready) {
+        (None, _) => "watching",
+        (Some(_), true) => "ready",
+        (Some(_), false) => "not ready",
+    }
+}
+
+/// Lists everyone in the lobby, players by paddle then those watching.
+fn update_lobby_list(
+    mut commands: Commands,
+    list: Single<Entity, With<LobbyList>>,
+    added: Query<(), Added<LobbyList>>,
+    changed: Query<(), Changed<LobbyPlayer>>,
+    mut removed: RemovedComponents<LobbyPlayer>,
+    players: Query<&LobbyPlayer>,
+) {
+    if added.is_empty() && changed.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+    let mut players: Vec<_> = players.iter().collect();
+    players.sort_by_key(|player| (player.slot.is_none(), player.slot));
+    commands
+        .entity(*list)
+        .despawn_related::<Children>()
+        .with_children(|list| {
+            for player in players {
+                list.spawn((
+                    Text::new(format!("{}  {}", player.info.name, status(player))),
+                    TextColor(player.info.color()),
+                    TextFont {
+                        font_size: 30.0,
+                        ..default()
+                    },
+                ));
+            }
+        });
+}
+
+/// Enter flips whether the local player is ready.
+fn toggle_ready(
+    keys: Res<ButtonInput<KeyCode>>,
+    local: Option<Res<LocalPaddle>>,
+    players: Query<&LobbyPlayer>,
+    mut ready: EventWriter<SetReady>,
+) {
+    if !keys.just_pressed(KeyCode::Enter) {
+        return;
+    }
+    let Some(local) = local else {
+        return;
+    };
+    if let Some(player) = players.iter().find(|player| player.slot == Some(local.0)) {
+        ready.write(SetReady(!player.ready));
+    }
+}
+
+/// Paints paddles in the colour of whoever plays them.
+fn tint_paddles(players: Query<&LobbyPlayer>, mut paddles: Query<(&Paddle, &mut Sprite)>) {
+    for (paddle, mut sprite) in &mut paddles {
+        let color = players
+            .iter()
+            .find(|player| player.slot == Some(paddle.player))
+            .map_or(TEXT, |player| player.info.color());
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
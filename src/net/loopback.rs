@@ -0,0 +1,219 @@
+//! In-memory transport for apps in the same process, standing in for
+//! `bevy_quinnet`.
+//!
+//! A server app and any number of client apps share a [`Loopback`], and
+//! replicon's messages are handed between them through queues instead of
+//! sockets. Single player runs its match through it, so it takes the same
+//! replication code path as a networked one, and tests use it to run a server
+//! and clients without ports, certificates or waiting on real time.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+use super::DisconnectRequest;
+
+/// Largest message replicon may send in one go, the size of a UDP datagram so
+/// messages are split up the way they would be over the network.
+pub const MAX_MESSAGE_SIZE: usize = 1200;
+
+/// Messages sent one way over a [`Link`], with the replicon channel they were
+/// sent on.
+type Queue = Mutex<VecDeque<(usize, Vec<u8>)>>;
+
+/// Connection between the server and one client.
+#[derive(Default)]
+struct Link {
+    to_server: Queue,
+    to_client: Queue,
+    closed: AtomicBool,
+}
+
+impl Link {
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+/// Where client apps connect to a server app in the same process. Clones
+/// share the same one, so a server and its clients each hold a copy.
+#[derive(Clone, Default)]
+pub struct Loopback {
+    /// Clients that connected since the server last looked
+    pending: Arc<Mutex<Vec<Arc<Link>>>>,
+}
+
+impl Loopback {
+    /// Opens a connection for a client app. The server accepts it on its next
+    /// update, and messages sent before then wait for it.
+    pub fn connect(&self) -> LoopbackClient {
+        let link = Arc::new(Link::default());
+        self.pending.lock().unwrap().push(link.clone());
+        LoopbackClient(link)
+    }
+}
+
+impl PartialEq for Loopback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pending, &other.pending)
+    }
+}
+
+impl fmt::Debug for Loopback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loopback").finish_non_exhaustive()
+    }
+}
+
+/// Makes this app a server clients can reach over the [`Loopback`].
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct LoopbackServer(pub Loopback);
+
+/// This app's connection to a server over a [`Loopback`].
+#[derive(Resource)]
+pub struct LoopbackClient(Arc<Link>);
+
+impl LoopbackClient {
+    /// Hangs up, the server sees the client leave on its next update.
+    pub fn disconnect(&self) {
+        self.0.close();
+    }
+}
+
+/// Server side end of a client's [`Link`].
+#[derive(Component)]
+struct LoopbackConnection(Arc<Link>);
+
+/// Moves replicon's messages over a [`Loopback`] for apps with a
+/// [`LoopbackServer`] or [`LoopbackClient`] resource.
+pub struct RepliconLoopbackPlugin;
+
+impl Plugin for RepliconLoopbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DisconnectRequest>();
+        app.add_systems(
+            PreUpdate,
+            (
+                (accept_clients, receive_from_clients)
+                    .chain()
+                    .in_set(ServerSet::ReceivePackets)
+                    .run_if(resource_exists::<LoopbackServer>),
+                receive_from_server
+                    .in_set(ClientSet::ReceivePackets)
+                    .run_if(resource_exists::<LoopbackClient>),
+            ),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                (send_to_clients, disconnect_clients)
+                    .chain()
+                    .in_set(ServerSet::SendPackets)
+                    .run_if(resource_exists::<LoopbackServer>),
+                send_to_server
+                    .in_set(ClientSet::SendPackets)
+                    .run_if(resource_exists::<LoopbackClient>),
+            ),
+        );
+    }
+}
+
+fn accept_clients(
+    mut commands: Commands,
+    loopback: Res<LoopbackServer>,
+    mut server: ResMut<RepliconServer>,
+) {
+    if !server.is_running() {
+        server.set_running(true);
+    }
+    for link in loopback.0.pending.lock().unwrap().drain(..) {
+        commands.spawn((
+            ConnectedClient {
+                max_size: MAX_MESSAGE_SIZE,
+            },
+            LoopbackConnection(link),
+        ));
+    }
+}
+
+fn receive_from_clients(
+    mut commands: Commands,
+    mut server: ResMut<RepliconServer>,
+    clients: Query<(Entity, &LoopbackConnection)>,
+) {
+    for (client, connection) in &clients {
+        if connection.0.is_closed() {
+            commands.entity(client).despawn();
+            continue;
+        }
+        for (channel, message) in connection.0.to_server.lock().unwrap().drain(..) {
+            server.insert_received(client, channel, message);
+        }
+    }
+}
+
+fn send_to_clients(mut server: ResMut<RepliconServer>, clients: Query<&LoopbackConnection>) {
+    for (client, channel, message) in server.drain_sent() {
+        let Ok(connection) = clients.get(client) else {
+            continue;
+        };
+        connection
+            .0
+            .to_client
+            .lock()
+            .unwrap()
+            .push_back((channel, message.to_vec()));
+    }
+}
+
+/// Hangs up on clients the server asked to disconnect, after anything sent
+/// to them this frame.
+fn disconnect_clients(
+    mut commands: Commands,
+    mut requests: EventReader<DisconnectRequest>,
+    clients: Query<&LoopbackConnection>,
+) {
+    for request in requests.read() {
+        let Ok(connection) = clients.get(request.client_entity) else {
+            continue;
+        };
+        connection.0.close();
+        commands.entity(request.client_entity).despawn();
+    }
+}
+
+fn receive_from_server(loopback: Res<LoopbackClient>, mut client: ResMut<RepliconClient>) {
+    if loopback.0.is_closed() {
+        if !client.is_disconnected() {
+            client.set_status(RepliconClientStatus::Disconnected);
+        }
+        return;
+    }
+    if client.is_disconnected() {
+        client.set_status(RepliconClientStatus::Connected);
+    }
+    for (channel, message) in loopback.0.to_client.lock().unwrap().drain(..) {
+        client.insert_received(channel, message);
+    }
+}
+
+fn send_to_server(loopback: Res<LoopbackClient>, mut client: ResMut<RepliconClient>) {
+    let mut to_server = loopback.0.to_server.lock().unwrap();
+    for (channel, message) in client.drain_sent() {
+        if !loopback.0.is_closed() {
+            to_server.push_back((channel, message.to_vec()));
+        }
+    }
+}
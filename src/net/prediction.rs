@@ -0,0 +1,182 @@
+//! Client side prediction for the paddle a client controls.
+//!
+//! Every [`PaddleInput`] a client sends carries a sequence number, and the
+//! client moves its paddle with it straight away instead of waiting a round
+//! trip for the server. The server moves the paddle with the same
+//! [`step_paddle`] as inputs arrive and answers with a [`PaddleAck`] holding
+//! the last sequence it applied and where that left the paddle.
+//!
+//! When an ack disagrees with what the client predicted for that input, e.g.
+//! because inputs were lost on the way, the client rewinds to the server's
+//! position and replays the inputs the server hasn't seen yet on top of it.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::PaddleInput;
+
+/// Lowest a paddle's center can go, matching where pong's physics stops it.
+pub const PADDLE_MIN_Y: f32 = 12.0;
+/// Highest a paddle's center can go.
+pub const PADDLE_MAX_Y: f32 = 88.0;
+/// Longest frame a single input can move a paddle for, so a client can't
+/// make its paddle jump by claiming a huge frame time.
+pub const MAX_INPUT_DT: f32 = 0.1;
+/// Most movement time a client can bank while none of its inputs arrive, so
+/// inputs held up on the way still play out once they do, but a client
+/// sending more than its share can't get further ahead than this.
+pub const INPUT_SLACK: f32 = 0.25;
+/// How far an ack can be from the prediction before the client rewinds.
+pub const RECONCILE_TOLERANCE: f32 = 0.01;
+/// Unacked inputs a client keeps before giving up on the oldest, about two
+/// seconds' worth at 60 frames per second.
+const MAX_PENDING: usize = 128;
+
+/// Sent to a client after the server applies its inputs.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PaddleAck {
+    /// [`PaddleInput::sequence`] of the last input applied
+    pub sequence: u32,
+    /// Height of the paddle once it was applied
+    pub y: f32,
+}
+
+/// Where `input` takes a paddle at height `y` moving at `speed`. The server
+/// and the predicting client both move paddles with this, so they agree
+/// whenever they saw the same inputs.
+pub fn step_paddle(y: f32, input: &PaddleInput, speed: f32) -> f32 {
+    let dt = input.dt.clamp(0.0, MAX_INPUT_DT);
+    (y + input.direction.clamp(-1.0, 1.0) * speed * dt).clamp(PADDLE_MIN_Y, PADDLE_MAX_Y)
+}
+
+/// Last input the server applied to a client's paddle.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LastInput(pub Option<u32>);
+
+impl LastInput {
+    /// Applies `input` to a paddle at height `y`, returning its new height.
+    /// Inputs arriving late, after a newer one was applied, are dropped.
+    pub fn apply(&mut self, y: f32, input: &PaddleInput, speed: f32) -> Option<f32> {
+        if self.0.is_some_and(|last| input.sequence <= last) {
+            return None;
+        }
+        self.0 = Some(input.sequence);
+        Some(step_paddle(y, input, speed))
+    }
+}
+
+/// Movement time a client's paddle has left. The server tops it up with its
+/// own clock, so a client can't move faster by claiming long frames or by
+/// sending more inputs than it has frames.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct InputBudget(pub f32);
+
+impl InputBudget {
+    /// Adds `elapsed` seconds of server time, keeping at most [`INPUT_SLACK`]
+    /// unspent.
+    pub fn refill(&mut self, elapsed: f32) {
+        self.0 = (self.0 + elapsed).min(INPUT_SLACK);
+    }
+
+    /// `input` cut short to the time left in the budget.
+    pub fn limit(&self, input: &PaddleInput) -> PaddleInput {
+        PaddleInput {
+            dt: input.dt.clamp(0.0, MAX_INPUT_DT).min(self.0),
+            ..*input
+        }
+    }
+
+    /// Takes the time an applied input moved for out of the budget.
+    pub fn spend(&mut self, input: &PaddleInput) {
+        self.0 = (self.0 - input.dt).max(0.0);
+    }
+}
+
+/// An input sent but not yet acked, along with where the client predicted it
+/// would leave the paddle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pending {
+    input: PaddleInput,
+    y: f32,
+}
+
+/// Predicted state of the paddle this client controls.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Prediction {
+    y: f32,
+    next_sequence: u32,
+    pending: VecDeque<Pending>,
+    rewinds: u32,
+}
+
+impl Prediction {
+    /// Starts predicting from a paddle at height `y`.
+    pub fn new(y: f32) -> Self {
+        Self {
+            y,
+            next_sequence: 0,
+            pending: VecDeque::new(),
+            rewinds: 0,
+        }
+    }
+
+    /// Predicted height of the paddle.
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    /// Number of inputs the server hasn't acked yet.
+    pub fn unacked(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// How many times an ack disagreed with the prediction.
+    pub fn rewinds(&self) -> u32 {
+        self.rewinds
+    }
+
+    /// Moves the paddle for a frame of `dt` seconds with `direction` held,
+    /// returning the input to send to the server.
+    pub fn predict(&mut self, direction: f32, dt: f32, speed: f32) -> PaddleInput {
+        let input = PaddleInput {
+            sequence: self.next_sequence,
+            direction,
+            dt,
+        };
+        self.next_sequence += 1;
+        self.y = step_paddle(self.y, &input, speed);
+        if self.pending.len() == MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(Pending { input, y: self.y });
+        input
+    }
+
+    /// Drops every input `ack` covers, and if the server ended up somewhere
+    /// else than predicted, replays the rest on top of where it says the
+    /// paddle is.
+    pub fn reconcile(&mut self, ack: &PaddleAck, speed: f32) {
+        let mut predicted = None;
+        while let Some(pending) = self.pending.front()
+            && pending.input.sequence <= ack.sequence
+        {
+            predicted = Some(pending.y);
+            self.pending.pop_front();
+        }
+        // An ack for inputs already dropped is older news than the last one
+        let Some(predicted) = predicted else {
+            return;
+        };
+        if (predicted - ack.y).abs() <= RECONCILE_TOLERANCE {
+            return;
+        }
+        self.rewinds += 1;
+        self.y = ack.y;
+        for pending in &mut self.pending {
+            self.y = step_paddle(self.y, &pending.input, speed);
+            pending.y = self.y;
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Handshake making sure client and server speak the same protocol.
+//!
+//! A client sends [`Hello`] with its [`Protocol`] as soon as it connects. The
+//! server answers with [`Welcome`] and starts replicating to it when both
+//! match, or with [`Rejected`] and a reason to show the player before
+//! disconnecting it. Nothing else is sent either way until then, so a client
+//! built from another version of the game can't misread what arrives.
+//!
+//! The protocol is made of [`PROTOCOL_VERSION`] and a hash of every
+//! component and event registered through [`ProtocolAppExt`]: the name and
+//! version each is registered under, whether it is a component or which way
+//! an event goes, its channel, and the order they come in. So adding,
+//! removing or reordering what is replicated is caught even without bumping
+//! the version.
+//!
+//! The hash can't see inside the types though. Changing a type's fields, or
+//! how serde writes them, goes unnoticed unless the version it is registered
+//! under is bumped along with it, and so does a system changing what a
+//! message means, which needs [`PROTOCOL_VERSION`] bumped.
+
+use bevy::{ecs::component::Mutable, platform::collections::HashSet, prelude::*};
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Bumped whenever client and server stop understanding each other in a way
+/// the hash of registered types doesn't catch, e.g. a system changing what an
+/// event means.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long the server waits for a [`Hello`] before disconnecting a client.
+pub const HANDSHAKE_TIMEOUT: f32 = 5.0;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// What an app sends and expects over the network, inserted as a resource by
+/// [`NetPongPlugin`](super::NetPongPlugin).
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protocol {
+    pub version: u32,
+    /// Hash of the name, version, kind and channel every type was registered
+    /// with, in the order they were registered
+    pub hash: u64,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            hash: FNV_OFFSET,
+        }
+    }
+}
+
+impl Protocol {
+    /// Mixes `part` into the hash. FNV-1a rather than std's hasher, which may
+    /// change between Rust releases, so builds that registered the same parts
+    /// agree.
+    pub fn add(&mut self, part: &str) {
+        // Each part ends with a 0, so "ab" then "c" differs from "a" then "bc"
+        for byte in part.bytes().chain([0]) {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Why a client speaking `client` can't join a server speaking `self`.
+    pub fn mismatch(&self, client: &Protocol) -> Option<String> {
+        if client.version != self.version {
+            Some(format!(
+                "The server runs protocol version {}, but you have version {}.\n\
+                 Update to the same version of the game to join.",
+                self.version, client.version
+            ))
+        } else if client.hash != self.hash {
+            Some(
+                "The server replicates different components and events than you do,\n\
+                 it was built from another version of the game.\n\
+                 Update to the same version of the game to join."
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    }
+}
+
+/// Sent by a client as soon as it connects.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hello(pub Protocol);
+
+/// Sent to a client whose [`Hello`] matched, after which it is replicated to.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Welcome;
+
+/// Sent to a client about to be disconnected, with why to show the player.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Rejected(pub String);
+
+/// Registers the handshake itself, before anything else so its channels stay
+/// the same from one version to the next.
+pub(super) fn register_handshake(app: &mut App) {
+    app.init_resource::<Protocol>();
+    app.add_client_event::<Hello>(Channel::Ordered);
+    app.add_server_event::<Welcome>(Channel::Ordered);
+    app.add_server_event::<Rejected>(Channel::Ordered);
+    // Sent before the client is replicated to, so they can't wait for
+    // replication to catch up
+    app.make_independent::<Welcome>();
+    app.make_independent::<Rejected>();
+}
+
+/// Replicon registration that also mixes what is registered into the app's
+/// [`Protocol`].
+///
+/// Each type is registered under a `name` of its own rather than its Rust
+/// path, which moves with refactors and isn't guaranteed to be the same from
+/// one compiler to the next. Names must be unique, and `version` is bumped
+/// whenever what goes over the wire for the type changes.
+pub trait ProtocolAppExt {
+    fn replicate_checked<C>(&mut self, name: &str, version: u32) -> &mut Self
+    where
+        C: Component<Mutability = Mutable> + Serialize + DeserializeOwned;
+
+    fn add_client_event_checked<E>(
+        &mut self,
+        name: &str,
+        version: u32,
+        channel: Channel,
+    ) -> &mut Self
+    where
+        E: Event + Serialize + DeserializeOwned;
+
+    fn add_server_event_checked<E>(
+        &mut self,
+        name: &str,
+        version: u32,
+        channel: Channel,
+    ) -> &mut Self
+    where
+        E: Event + Serialize + DeserializeOwned;
+}
+
+impl ProtocolAppExt for App {
+    fn replicate_checked<C>(&mut self, name: &str, version: u32) -> &mut Self
+    where
+        C: Component<Mutability = Mutable> + Serialize + DeserializeOwned,
+    {
+        add_to_protocol(self, "component", name, version, None);
+        self.replicate::<C>()
+    }
+
+    fn add_client_event_checked<E>(
+        &mut self,
+        name: &str,
+        version: u32,
+        channel: Channel,
+    ) -> &mut Self
+    where
+        E: Event + Serialize + DeserializeOwned,
+    {
+        add_to_protocol(self, "client event", name, version, Some(channel));
+        self.add_client_event::<E>(channel)
+    }
+
+    fn add_server_event_checked<E>(
+        &mut self,
+        name: &str,
+        version: u32,
+        channel: Channel,
+    ) -> &mut Self
+    where
+        E: Event + Serialize + DeserializeOwned,
+    {
+        add_to_protocol(self, "server event", name, version, Some(channel));
+        self.add_server_event::<E>(channel)
+    }
+}
+
+/// Names registered so far, to catch two types sharing one.
+#[derive(Resource, Default)]
+struct RegisteredNames(HashSet<String>);
+
+fn add_to_protocol(app: &mut App, kind: &str, name: &str, version: u32, channel: Option<Channel>) {
+    let mut names = app.world_mut().get_resource_or_init::<RegisteredNames>();
+    assert!(
+        names.0.insert(name.to_string()),
+        "`{name}` is registered with the protocol twice"
+    );
+    let mut protocol = app.world_mut().get_resource_or_init::<Protocol>();
+    protocol.add(kind);
+    protocol.add(name);
+    protocol.add(&version.to_string());
+    if let Some(channel) = channel {
+        protocol.add(&format!("{channel:?}"));
+    }
+}
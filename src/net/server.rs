@@ -0,0 +1,572 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_quinnet::server::{
+    QuinnetServer, ServerEndpointConfiguration, certificate::CertificateRetrievalMode,
+};
+use bevy_replicon::{prelude::*, shared::backend::connected_client::NetworkId};
+use bevy_replicon_quinnet::ChannelsConfigurationExt;
+
+use super::{
+    AssignedPaddle, DisconnectRequest, Hello, InputBudget, JoinLobby, LastInput, LobbyPlayer,
+    LocalPaddle, LocalPlayer, LoopbackServer, MatchStarted, NetPhase, NetRole, NetScore, NetState,
+    PaddleAck, PaddleInput, PaddleOwner, PaddleSlot, Protocol, Rejected, ServerCertificate,
+    SetReady, Transport, Welcome, conditions::DelayLines, protocol::HANDSHAKE_TIMEOUT,
+};
+use crate::pong::{
+    AiController, Ball, GamePhase, MatchWinner, Paddle, PongConfig, Position, RemoteControlled,
+    Score, Speed, Velocity,
+};
+
+pub(super) fn build(app: &mut App) {
+    app.add_event::<DisconnectRequest>();
+    app.add_systems(Startup, (start_server, spawn_net_score));
+    app.add_systems(PostStartup, join_host.run_if(not(dedicated)));
+    app.add_observer(replicate_ball);
+    app.add_observer(replicate_paddle);
+    app.add_observer(greet);
+    app.add_observer(leave);
+    app.add_systems(
+        PreUpdate,
+        (
+            hold_match.run_if(in_state(NetState::Lobby)),
+            (
+                check_hello,
+                join_lobby,
+                set_ready.run_if(in_state(NetState::Lobby)),
+                (refill_input_budgets, apply_paddle_input)
+                    .chain()
+                    .run_if(in_state(NetState::InGame)),
+            )
+                .chain()
+                .after(ServerSet::Receive)
+                .run_if(server_running),
+        ),
+    );
+    app.add_systems(
+        Update,
+        (
+            end_handshakes,
+            start_when_ready.run_if(in_state(NetState::Lobby)),
+        ),
+    );
+    app.add_systems(OnExit(NetState::Lobby), release_match);
+    app.add_systems(
+        PostUpdate,
+        (
+            (sync_score, sync_phase).before(ServerSet::Send),
+            disconnect_clients.after(ServerSet::SendPackets),
+        ),
+    );
+}
+
+fn start_server(
+    mut commands: Commands,
+    mut server: ResMut<QuinnetServer>,
+    channels: Res<RepliconChannels>,
+    role: Res<NetRole>,
+) -> Result {
+    let NetRole::Server(settings) = &*role else {
+        return Ok(());
+    };
+    if let Transport::Loopback(loopback) = &settings.transport {
+        commands.insert_resource(LoopbackServer(loopback.clone()));
+        return Ok(());
+    }
+    let certificate = match &settings.certificate {
+        ServerCertificate::SelfSigned => CertificateRetrievalMode::GenerateSelfSigned {
+            server_hostname: settings.bind.to_string(),
+        },
+        ServerCertificate::Files { cert, key } => CertificateRetrievalMode::LoadFromFile {
+            cert_file: cert.to_string_lossy().into_owned(),
+            key_file: key.to_string_lossy().into_owned(),
+        },
+        ServerCertificate::Persisted { cert, key } => {
+            for file in [cert, key] {
+                if let Some(dir) = file.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+            }
+            CertificateRetrievalMode::LoadFromFileOrGenerateSelfSigned {
+                cert_file: cert.to_string_lossy().into_owned(),
+                key_file: key.to_string_lossy().into_owned(),
+                save_on_disk: true,
+                server_hostname: settings.bind.to_string(),
+            }
+        }
+    };
+    let certificate = server.start_endpoint(
+        ServerEndpointConfiguration::from_ip(settings.bind, settings.port),
+        certificate,
+        channels.server_configs(),
+    )?;
+    info!(
+        "Server listening on {} port {}, for up to {} clients",
+        settings.bind, settings.port, settings.max_clients
+    );
+    // Players can pass this to `--trust` to know they reached this server
+    info!(
+        "Certificate fingerprint: {}",
+        certificate.fingerprint.to_base64()
+    );
+    Ok(())
+}
+
+fn dedicated(role: Res<NetRole>) -> bool {
+    matches!(&*role, NetRole::Server(settings) if settings.dedicated)
+}
+
+/// Seconds a rejected client is given to read why before it is disconnected,
+/// counted from when simulated conditions let the rejection go.
+const REJECTION_GRACE: f32 = 0.5;
+
+/// Client that hasn't been let in yet, disconnected once the timer runs out.
+#[derive(Component, Debug)]
+struct Handshake {
+    timer: Timer,
+    rejected: bool,
+}
+
+impl Handshake {
+    fn pending() -> Self {
+        Self {
+            timer: Timer::from_seconds(HANDSHAKE_TIMEOUT, TimerMode::Once),
+            rejected: false,
+        }
+    }
+
+    fn rejected() -> Self {
+        Self {
+            timer: Timer::from_seconds(REJECTION_GRACE, TimerMode::Once),
+            rejected: true,
+        }
+    }
+}
+
+/// Waits for the [`Hello`] of clients as they connect, and turns away those
+/// past [`ServerSettings::max_clients`].
+///
+/// [`ServerSettings::max_clients`]: super::ServerSettings::max_clients
+fn greet(
+    trigger: Trigger<OnAdd, ConnectedClient>,
+    mut commands: Commands,
+    role: Res<NetRole>,
+    clients: Query<(), With<ConnectedClient>>,
+    mut rejected: EventWriter<ToClients<Rejected>>,
+) {
+    let NetRole::Server(settings) = &*role else {
+        return;
+    };
+    let client = trigger.target();
+    if clients.iter().count() <= settings.max_clients {
+        commands.entity(client).insert(Handshake::pending());
+        return;
+    }
+    info!("`{client}` turned away, the server is full");
+    commands.entity(client).insert(Handshake::rejected());
+    rejected.write(ToClients {
+        mode: SendMode::Direct(client),
+        event: Rejected(format!(
+            "The server is full, it lets in {} players at once.",
+            settings.max_clients
+        )),
+    });
+}
+
+/// Lets in clients speaking the server's [`Protocol`], and tells the others
+/// why they are turned away.
+fn check_hello(
+    mut commands: Commands,
+    mut hellos: EventReader<FromClient<Hello>>,
+    protocol: Res<Protocol>,
+    mut handshakes: Query<&mut Handshake>,
+    mut welcome: EventWriter<ToClients<Welcome>>,
+    mut rejected: EventWriter<ToClients<Rejected>>,
+) {
+    for FromClient {
+        client_entity,
+        event,
+    } in hellos.read()
+    {
+        let client = *client_entity;
+        let Ok(mut handshake) = handshakes.get_mut(client) else {
+            continue;
+        };
+        if handshake.rejected {
+            continue;
+        }
+        match protocol.mismatch(&event.0) {
+            None => {
+                commands
+                    .entity(client)
+                    .remove::<Handshake>()
+                    .insert(ReplicatedClient);
+                welcome.write(ToClients {
+                    mode: SendMode::Direct(client),
+                    event: Welcome,
+                });
+            }
+            Some(reason) => {
+                info!(
+                    "`{client}` turned away, it speaks {:?} but the server {:?}",
+                    event.0, *protocol
+                );
+                *handshake = Handshake::rejected();
+                rejected.write(ToClients {
+                    mode: SendMode::Direct(client),
+                    event: Rejected(reason),
+                });
+            }
+        }
+    }
+}
+
+/// Disconnects clients that were rejected or never said hello.
+fn end_handshakes(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    lines: Res<DelayLines>,
+    mut handshakes: Query<(Entity, &mut Handshake)>,
+    mut disconnect: EventWriter<DisconnectRequest>,
+) {
+    for (client, mut handshake) in &mut handshakes {
+        // Hanging up now would drop the rejection along with what's held
+        if handshake.rejected && lines.holds_for_client(client) {
+            continue;
+        }
+        if !handshake.timer.tick(time.delta()).finished() {
+            continue;
+        }
+        if !handshake.rejected {
+            info!("`{client}` never said hello, disconnecting it");
+        }
+        commands.entity(client).remove::<Handshake>();
+        disconnect.write(DisconnectRequest {
+            client_entity: client,
+        });
+    }
+}
+
+/// Hangs up on clients connected over QUIC, the loopback transport handles
+/// its own.
+fn disconnect_clients(
+    mut commands: Commands,
+    mut requests: EventReader<DisconnectRequest>,
+    mut server: ResMut<QuinnetServer>,
+    clients: Query<&NetworkId>,
+) {
+    for request in requests.read() {
+        let Ok(network_id) = clients.get(request.client_entity) else {
+            continue;
+        };
+        if let Some(endpoint) = server.get_endpoint_mut()
+            && let Err(error) = endpoint.disconnect_client(network_id.get())
+        {
+            warn!("Couldn't disconnect `{}`: {error}", request.client_entity);
+        }
+        // Quinnet doesn't report clients it was told to disconnect as lost
+        commands.entity(request.client_entity).despawn();
+    }
+}
+
+fn spawn_net_score(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Score"),
+        NetScore::default(),
+        NetPhase::default(),
+        Replicated,
+    ));
+}
+
+fn replicate_ball(trigger: Trigger<OnAdd, Ball>, mut commands: Commands) {
+    commands.entity(trigger.target()).insert(Replicated);
+}
+
+/// Replicates paddles as they spawn.
+fn replicate_paddle(
+    trigger: Trigger<OnAdd, Paddle>,
+    mut commands: Commands,
+    paddles: Query<&Paddle>,
+) {
+    let Ok(paddle) = paddles.get(trigger.target()) else {
+        return;
+    };
+    commands
+        .entity(trigger.target())
+        .insert((Replicated, PaddleSlot(paddle.player)));
+}
+
+/// The host is in the lobby from the start, playing the paddle its own
+/// keyboard controls. Pong hands the computer its paddles after spawning
+/// them, so this waits until then to tell them apart.
+fn join_host(
+    mut commands: Commands,
+    local: Res<LocalPlayer>,
+    paddles: Query<(Entity, &Paddle), Without<AiController>>,
+) {
+    let slot = paddles
+        .iter()
+        .min_by_key(|(_, paddle)| paddle.player)
+        .map(|(entity, paddle)| {
+            commands.entity(entity).insert(PaddleOwner(SERVER));
+            paddle.player
+        });
+    if let Some(slot) = slot {
+        commands.insert_resource(LocalPaddle(slot));
+    }
+    commands.spawn((
+        Name::new("Host"),
+        LobbyPlayer {
+            info: local.sanitized(|| "Host".to_string()),
+            slot,
+            ready: false,
+        },
+        LobbyOwner(SERVER),
+        Replicated,
+    ));
+}
+
+/// Client entity a [`LobbyPlayer`] belongs to.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+struct LobbyOwner(Entity);
+
+/// Gives each client joining the first paddle the computer is playing, or a
+/// place to watch from when there is none. Only clients that passed the
+/// handshake can join.
+#[allow(clippy::too_many_arguments)]
+fn join_lobby(
+    mut commands: Commands,
+    mut joins: EventReader<FromClient<JoinLobby>>,
+    welcomed: Query<(), With<ReplicatedClient>>,
+    state: Res<State<NetState>>,
+    mut members: Query<(&LobbyOwner, &mut LobbyPlayer)>,
+    paddles: Query<(Entity, &Paddle), Without<PaddleOwner>>,
+    mut assigned: EventWriter<ToClients<AssignedPaddle>>,
+    mut started: EventWriter<ToClients<MatchStarted>>,
+) {
+    let mut taken = Vec::new();
+    for FromClient {
+        client_entity,
+        event,
+    } in joins.read()
+    {
+        let client = *client_entity;
+        if !welcomed.contains(client) {
+            continue;
+        }
+        let number = members.iter().count() + 1;
+        let info = event.0.sanitized(|| format!("Player {number}"));
+        if let Some((_, mut member)) = members.iter_mut().find(|(owner, _)| owner.0 == client) {
+            member.info = info;
+            continue;
+        }
+
+        let free = paddles
+            .iter()
+            .filter(|(entity, _)| !taken.contains(entity))
+            .min_by_key(|(_, paddle)| paddle.player);
+        let slot = free.map(|(entity, paddle)| {
+            taken.push(entity);
+            commands
+                .entity(entity)
+                .insert((
+                    PaddleOwner(client),
+                    RemoteControlled,
+                    LastInput::default(),
+                    InputBudget::default(),
+                ))
+                .remove::<AiController>();
+            assigned.write(ToClients {
+                mode: SendMode::Direct(client),
+                event: AssignedPaddle {
+                    player: paddle.player,
+                },
+            });
+            paddle.player
+        });
+        match slot {
+            Some(slot) => info!("{} joined with paddle {slot}", info.name),
+            None => info!("{} joined to watch, every paddle is taken", info.name),
+        }
+        commands.spawn((
+            Name::new(info.name.clone()),
+            LobbyPlayer {
+                info,
+                slot,
+                ready: false,
+            },
+            LobbyOwner(client),
+            Replicated,
+        ));
+        if *state.get() == NetState::InGame {
+            started.write(ToClients {
+                mode: SendMode::Direct(client),
+                event: MatchStarted,
+            });
+        }
+    }
+}
+
+fn set_ready(
+    mut events: EventReader<FromClient<SetReady>>,
+    mut members: Query<(&LobbyOwner, &mut LobbyPlayer)>,
+) {
+    for FromClient {
+        client_entity,
+        event,
+    } in events.read()
+    {
+        for (owner, mut member) in &mut members {
+            if owner.0 == *client_entity && member.slot.is_some() {
+                member.ready = event.0;
+            }
+        }
+    }
+}
+
+/// Starts the match once every player is ready. Those only watching don't
+/// hold it up, and paddles nobody joined for are left to the computer.
+fn start_when_ready(
+    members: Query<&LobbyPlayer>,
+    mut next_state: ResMut<NextState<NetState>>,
+    mut started: EventWriter<ToClients<MatchStarted>>,
+) {
+    let players: Vec<_> = members
+        .iter()
+        .filter(|member| member.slot.is_some())
+        .collect();
+    if players.is_empty() || !players.iter().all(|member| member.ready) {
+        return;
+    }
+    info!("Everyone is ready, starting the match");
+    next_state.set(NetState::InGame);
+    started.write(ToClients {
+        mode: SendMode::Broadcast,
+        event: MatchStarted,
+    });
+}
+
+/// Keeps the match from starting while the lobby is open.
+fn hold_match(mut config: ResMut<PongConfig>) {
+    if config.simulate {
+        config.simulate = false;
+    }
+}
+
+fn release_match(mut config: ResMut<PongConfig>) {
+    config.simulate = true;
+}
+
+/// Frees the place of a client that left, handing its paddle back to the
+/// computer.
+fn leave(
+    trigger: Trigger<OnRemove, ConnectedClient>,
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    mut paddles: Query<(Entity, &PaddleOwner, &mut Paddle)>,
+    members: Query<(Entity, &LobbyOwner, &LobbyPlayer)>,
+) {
+    let client = trigger.target();
+    for (entity, owner, mut paddle) in &mut paddles {
+        if owner.0 != client {
+            continue;
+        }
+        info!(
+            "`{client}` left, the computer takes paddle {}",
+            paddle.player
+        );
+        paddle.input_direction = default();
+        commands
+            .entity(entity)
+            .remove::<(PaddleOwner, RemoteControlled, LastInput, InputBudget)>()
+            .insert(AiController::new(config.difficulty));
+    }
+    for (entity, owner, member) in &members {
+        if owner.0 == client {
+            info!("{} left the lobby", member.info.name);
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Gives every client the time that passed on the server to move its paddle
+/// with.
+fn refill_input_budgets(time: Res<Time<Real>>, mut budgets: Query<&mut InputBudget>) {
+    for mut budget in &mut budgets {
+        budget.refill(time.delta_secs());
+    }
+}
+
+/// Moves client paddles with each input as it arrives, as far as their budget
+/// allows, and tells every client that sent some where its paddle ended up.
+fn apply_paddle_input(
+    mut inputs: EventReader<FromClient<PaddleInput>>,
+    mut paddles: Query<(
+        &PaddleOwner,
+        &mut LastInput,
+        &mut InputBudget,
+        &Speed,
+        &mut Position,
+        &mut Velocity,
+    )>,
+    mut acks: EventWriter<ToClients<PaddleAck>>,
+) {
+    let mut acked = Vec::new();
+    for FromClient {
+        client_entity,
+        event,
+    } in inputs.read()
+    {
+        for (owner, mut last, mut budget, speed, mut position, mut velocity) in &mut paddles {
+            if owner.0 != *client_entity {
+                continue;
+            }
+            let input = budget.limit(event);
+            let Some(y) = last.apply(position.0.y, &input, speed.0) else {
+                continue;
+            };
+            budget.spend(&input);
+            position.0.y = y;
+            // Only read to spin the ball, the input already moved the paddle
+            velocity.0.y = event.direction.clamp(-1.0, 1.0) * speed.0;
+            acked.retain(|(client, _)| client != client_entity);
+            acked.push((
+                *client_entity,
+                PaddleAck {
+                    sequence: event.sequence,
+                    y,
+                },
+            ));
+        }
+    }
+    for (client, ack) in acked {
+        acks.write(ToClients {
+            mode: SendMode::Direct(client),
+            event: ack,
+        });
+    }
+}
+
+fn sync_score(score: Res<Score>, mut net_score: Single<&mut NetScore>) {
+    if score.is_changed() {
+        **net_score = NetScore {
+            player: score.player,
+            ai: score.ai,
+        };
+    }
+}
+
+fn sync_phase(
+    phase: Option<Res<State<GamePhase>>>,
+    winner: Option<Res<MatchWinner>>,
+    mut net_phase: Single<&mut NetPhase>,
+) {
+    // Paused or not, the match stays in its phase
+    let Some(phase) = phase else {
+        return;
+    };
+    net_phase.set_if_neq(NetPhase {
+        phase: phase.get().clone(),
+        winner: winner.map(|winner| winner.0),
+    });
+}
@@ -0,0 +1,393 @@
+//! Pong gameplay, packaged as a [`PongPlugin`] that can be added to any app.
+//!
+//! The playfield uses its own coordinate space (see [`Position`] and
+//! [`Shape`]) which is mapped onto the primary window, or onto
+//! [`HEADLESS_RESOLUTION`] when there is none, so the plugin also runs under
+//! `MinimalPlugins`.
+//!
+//! The simulation runs in [`FixedUpdate`] at [`PongConfig::tick_rate`], so the
+//! same inputs produce the same rally regardless of frame rate. [`Transform`]s
+//! are interpolated between ticks in [`PostUpdate`].
+
+mod ai;
+pub mod components;
+mod input;
+mod menu;
+mod physics;
+mod render;
+mod rules;
+mod scoring;
+
+use bevy::{input::InputSystem, prelude::*};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+#[cfg(feature = "net")]
+use serde::{Deserialize, Serialize};
+
+pub use ai::{AiController, AiProfile, Difficulty, predict_intercept};
+pub use components::*;
+pub use input::{Action, GameMode, GamepadSlot, InputBindings, PlayerBindings, ServeRequested};
+pub use menu::MenuButton;
+pub use physics::{Collision, SweptCollision, sweep};
+pub use render::HEADLESS_RESOLUTION;
+pub use rules::{MatchClock, MatchRules, MatchWinner, ServeRule};
+
+use crate::apply_fixed_tick_transitions;
+
+pub const BASE: Color = Color::srgb_u8(30, 30, 46);
+pub const TEXT: Color = Color::srgb_u8(205, 214, 244);
+pub const GREEN: Color = Color::srgb_u8(166, 227, 161);
+pub const RED: Color = Color::srgb_u8(243, 139, 168);
+
+/// Tunables for a game of pong, inserted as a resource by [`PongPlugin`].
+#[derive(Resource, Clone, Debug)]
+pub struct PongConfig {
+    /// Speed of the ball when it is served, in playfield units per second
+    pub ball_speed: f32,
+    /// Maximum speed of the paddles, in playfield units per second
+    pub paddle_speed: f32,
+    /// Seconds between the start of the game and the first serve
+    pub start_delay: f32,
+    /// Seconds between a point and the next serve
+    pub score_delay: f32,
+    /// Simulation ticks per second
+    pub tick_rate: f64,
+    /// Who controls each paddle
+    pub mode: GameMode,
+    /// Key and gamepad bindings, the defaults for [`Self::mode`] when `None`
+    pub bindings: Option<InputBindings>,
+    /// How well computer controlled paddles play
+    pub difficulty: Difficulty,
+    /// Seed for serve directions, random when `None`
+    pub seed: Option<u64>,
+    /// How the match is won
+    pub rules: MatchRules,
+    /// Run the match here. Network clients turn this off and only draw the
+    /// ball and paddles the server sends them
+    pub simulate: bool,
+}
+
+impl Default for PongConfig {
+    fn default() -> Self {
+        Self {
+            ball_speed: 40.0,
+            paddle_speed: 80.0,
+            start_delay: 2.0,
+            score_delay: 1.0,
+            tick_rate: 64.0,
+            mode: GameMode::default(),
+            bindings: None,
+            difficulty: Difficulty::default(),
+            seed: None,
+            rules: MatchRules::default(),
+            simulate: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PongPlugin {
+    pub config: PongConfig,
+}
+
+impl Plugin for PongPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone());
+        app.insert_resource(PongRng(match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        }));
+        app.insert_resource(StartupTimer(Timer::from_seconds(
+            self.config.start_delay,
+            TimerMode::Once,
+        )));
+        app.insert_resource(rules::ScoringTimer(Timer::from_seconds(
+            self.config.score_delay,
+            TimerMode::Once,
+        )));
+        app.insert_resource(self.config.rules.clone());
+        app.insert_resource(
+            self.config
+                .bindings
+                .clone()
+                .unwrap_or_else(|| InputBindings::for_mode(self.config.mode)),
+        );
+        app.insert_resource(Time::<Fixed>::from_hz(self.config.tick_rate));
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            PreUpdate,
+            (
+                (input::assign_gamepads, input::handle_player_input).chain(),
+                input::handle_debug_keys.run_if(simulating),
+            )
+                .after(InputSystem),
+        );
+        app.add_systems(
+            FixedFirst,
+            (
+                apply_fixed_tick_transitions::<GamePhase>,
+                physics::store_previous_positions,
+            ),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                rules::tick_match_clock,
+                ai::drive_ai_paddles,
+                physics::apply_paddle_input,
+                physics::apply_drag,
+                physics::apply_velocity,
+                physics::handle_collisions,
+                physics::kill_offscreen,
+                scoring::detect_scoring,
+                scoring::reset_ball,
+                scoring::update_score,
+                rules::apply_match_rules,
+            )
+                .run_if(in_state(GamePhase::Rally).and(simulating))
+                .chain(),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (game_startup).run_if(in_state(GamePhase::Starting).and(simulating)),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (rules::finish_scoring).run_if(in_state(GamePhase::Scoring).and(simulating)),
+        );
+        app.add_systems(OnEnter(GamePhase::Scoring), rules::reset_scoring_timer);
+        app.add_systems(OnEnter(GamePhase::GameOver), menu::spawn_results_screen);
+        app.add_systems(Update, scoring::update_score_display);
+        app.add_systems(OnEnter(GamePhase::Starting), reset_startup_timer);
+        app.add_systems(
+            OnEnter(GameState::Paused),
+            (menu::pause_time, menu::spawn_pause_menu),
+        );
+        app.add_systems(OnExit(GameState::Paused), menu::unpause_time);
+        app.add_systems(
+            Update,
+            (menu::handle_menu_buttons, menu::highlight_menu_buttons)
+                .run_if(in_state(GameState::Paused).or(in_state(GamePhase::GameOver))),
+        );
+        app.add_systems(
+            Update,
+            restart_match
+                .after(menu::handle_menu_buttons)
+                .run_if(simulating),
+        );
+        app.add_systems(
+            PostUpdate,
+            (render::position_translation, render::scale_to_window).chain(),
+        );
+        app.add_systems(
+            PostUpdate,
+            (render::draw_box_collider_gizmos).run_if(in_state(DebugMode::Debug)),
+        );
+        app.init_state::<GameState>();
+        app.add_sub_state::<GamePhase>();
+        app.enable_state_scoped_entities::<GameState>();
+        app.enable_state_scoped_entities::<GamePhase>();
+        app.init_state::<DebugMode>();
+        app.add_event::<ScoredEvent>();
+        app.add_event::<RestartMatch>();
+        app.add_event::<ServeRequested>();
+        app.init_resource::<Score>();
+        app.init_resource::<rules::MatchClock>();
+        app.init_resource::<rules::NextServe>();
+    }
+}
+
+#[derive(Resource)]
+struct StartupTimer(Timer);
+
+fn simulating(config: Res<PongConfig>) -> bool {
+    config.simulate
+}
+
+/// Source of randomness for serves, seeded from [`PongConfig::seed`].
+#[derive(Resource)]
+pub struct PongRng(pub StdRng);
+
+#[derive(States, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum DebugMode {
+    #[default]
+    None,
+    Debug,
+}
+
+#[derive(States, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum GameState {
+    #[default]
+    Playing,
+    Paused,
+}
+
+/// Progress of the match, kept while [`GameState::Paused`] so resuming picks
+/// up where the game left off. The simulation itself is frozen by pausing
+/// [`Time<Virtual>`].
+#[derive(SubStates, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "net", derive(Serialize, Deserialize))]
+#[source(GameState = GameState::Playing | GameState::Paused)]
+pub enum GamePhase {
+    #[default]
+    Starting,
+    Rally,
+    /// Short break after a point before the next serve
+    Scoring,
+    /// The match is decided, see [`MatchWinner`]
+    GameOver,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ScoredEvent(pub Scorer);
+
+/// Resets the score, ball and paddles and starts a new countdown.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestartMatch;
+
+#[derive(Resource, Default, Debug)]
+pub struct Score {
+    pub player: u32,
+    pub ai: u32,
+}
+
+fn setup(mut commands: Commands, config: Res<PongConfig>, mut rng: ResMut<PongRng>) {
+    // Spawn Camera
+    commands.spawn(Camera2d);
+
+    // Spawn Barriers
+    commands.spawn((
+        Position(Vec2 { x: 50.0, y: 102.5 }),
+        Shape(Vec2 { x: 100.0, y: 5.0 }),
+        BoxCollider {
+            kinematic: true,
+            ..Default::default()
+        },
+    ));
+
+    commands.spawn((
+        Position(Vec2 { x: 50.0, y: -2.5 }),
+        Shape(Vec2 { x: 100.0, y: 5.0 }),
+        BoxCollider {
+            kinematic: true,
+            ..Default::default()
+        },
+    ));
+
+    spawn_score_cards(&mut commands);
+    if !config.simulate {
+        return;
+    }
+
+    // Spawn Paddles
+    for (player, name, x) in [(1, "Left Paddle", 10.0), (2, "Right Paddle", 90.0)] {
+        let mut paddle = commands.spawn((
+            Name::new(name),
+            Paddle {
+                player,
+                ..default()
+            },
+            Position(Vec2 { x, y: 50.0 }),
+            Speed(config.paddle_speed),
+        ));
+        if !config.mode.human_players().contains(&player) {
+            paddle.insert(AiController::new(config.difficulty));
+        }
+    }
+
+    // Spawn Ball
+    spawn_ball(&mut commands, &config, &mut rng);
+}
+
+fn spawn_score_cards(commands: &mut Commands) {
+    commands.spawn((
+        ScoreCard,
+        Scorer::Player,
+        Node {
+            top: Val::Percent(10.0),
+            left: Val::Percent(20.0),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        ScoreCard,
+        Scorer::Ai,
+        Node {
+            top: Val::Percent(10.0),
+            left: Val::Percent(80.0),
+            ..default()
+        },
+    ));
+}
+
+fn spawn_ball(commands: &mut Commands, config: &PongConfig, rng: &mut PongRng) {
+    let direction = if rng.0.random_bool(0.5) { -1.0 } else { 1.0 };
+    commands.spawn((
+        Ball,
+        Speed(config.ball_speed),
+        Velocity(serve_velocity(direction, config, rng)),
+    ));
+}
+
+/// Velocity of a serve heading left (`direction < 0`) or right at a random
+/// angle
+fn serve_velocity(direction: f32, config: &PongConfig, rng: &mut PongRng) -> Vec2 {
+    Vec2::new(direction.signum(), rng.0.random_range(-1.0..1.0)) * config.ball_speed
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restart_match(
+    mut commands: Commands,
+    mut events: EventReader<RestartMatch>,
+    config: Res<PongConfig>,
+    mut rng: ResMut<PongRng>,
+    mut score: ResMut<Score>,
+    mut clock: ResMut<MatchClock>,
+    mut timer: ResMut<StartupTimer>,
+    balls: Query<Entity, With<Ball>>,
+    mut paddles: Query<(
+        &mut Paddle,
+        &mut Position,
+        &mut PreviousPosition,
+        &mut Velocity,
+    )>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    if events.read().last().is_none() {
+        return;
+    }
+
+    *score = Score::default();
+    *clock = MatchClock::default();
+    commands.remove_resource::<MatchWinner>();
+    timer.0.reset();
+    for entity in &balls {
+        commands.entity(entity).despawn();
+    }
+    spawn_ball(&mut commands, &config, &mut rng);
+    for (mut paddle, mut position, mut previous, mut velocity) in &mut paddles {
+        paddle.input_direction = InputDirection::default();
+        position.0.y = 50.0;
+        previous.0 = position.0;
+        velocity.0 = Vec2::ZERO;
+    }
+    next_state.set(GameState::Playing);
+    next_phase.set(GamePhase::Starting);
+}
+
+fn reset_startup_timer(mut timer: ResMut<StartupTimer>) {
+    timer.0.reset();
+}
+
+fn game_startup(
+    time: Res<Time>,
+    mut timer: ResMut<StartupTimer>,
+    mut serve: EventReader<ServeRequested>,
+    mut next_state: ResMut<NextState<GamePhase>>,
+) {
+    timer.0.tick(time.delta());
+    if timer.0.just_finished() || serve.read().count() > 0 {
+        next_state.set(GamePhase::Rally);
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use clap::ValueEnum;
+use rand::Rng;
+
+use super::{
+    PongRng,
+    components::{Ball, Paddle, Position, Shape, Velocity},
+};
+
+/// Distance from its target at which an AI paddle starts slowing down
+const SLOWDOWN_DISTANCE: f32 = 6.0;
+
+/// Distance from its target at which an AI paddle stops steering
+const DEAD_ZONE: f32 = 0.5;
+
+/// Difficulty presets for [`AiController`].
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn profile(self) -> AiProfile {
+        match self {
+            Difficulty::Easy => AiProfile {
+                reaction_time: 0.5,
+                predict_bounces: false,
+                aim_error: 20.0,
+                max_speed: 0.5,
+            },
+            Difficulty::Medium => AiProfile {
+                reaction_time: 0.25,
+                predict_bounces: true,
+                aim_error: 16.0,
+                max_speed: 0.75,
+            },
+            Difficulty::Hard => AiProfile {
+                reaction_time: 0.1,
+                predict_bounces: true,
+                aim_error: 13.0,
+                max_speed: 1.0,
+            },
+        }
+    }
+}
+
+/// How an [`AiController`] plays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AiProfile {
+    /// Seconds between looks at the ball
+    pub reaction_time: f32,
+    /// Whether to aim for where the ball will cross the paddle, including
+    /// bounces off the barriers, rather than where it is now
+    pub predict_bounces: bool,
+    /// Largest random offset added to the target, in playfield units
+    pub aim_error: f32,
+    /// Fraction of the paddle's [`Speed`](super::Speed) the AI will use
+    pub max_speed: f32,
+}
+
+/// Steers a [`Paddle`] by setting its input direction, the same way a player
+/// does, so it is bound by the paddle's speed and limits.
+#[derive(Component, Debug, Clone)]
+pub struct AiController {
+    pub profile: AiProfile,
+    target: f32,
+    /// Aim error for the ball currently heading this way, rolled once per
+    /// approach so it can not average out over several looks
+    aim_offset: Option<f32>,
+    reaction: Timer,
+}
+
+impl AiController {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self::from_profile(difficulty.profile())
+    }
+
+    pub fn from_profile(profile: AiProfile) -> Self {
+        Self {
+            profile,
+            target: 50.0,
+            aim_offset: None,
+            reaction: Timer::from_seconds(profile.reaction_time, TimerMode::Repeating),
+        }
+    }
+
+    /// Height the paddle is currently heading for
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+}
+
+/// Where a ball at `position` moving with `velocity` will be vertically when
+/// it reaches `target_x`, reflecting off horizontal walls at `min_y` and
+/// `max_y`. Returns `None` if the ball is not heading towards `target_x`.
+pub fn predict_intercept(
+    position: Vec2,
+    velocity: Vec2,
+    target_x: f32,
+    min_y: f32,
+    max_y: f32,
+) -> Option<f32> {
+    let time = (target_x - position.x) / velocity.x;
+    if !time.is_finite() || time < 0.0 {
+        return None;
+    }
+
+    let height = max_y - min_y;
+    if height <= 0.0 {
+        return Some(min_y);
+    }
+    // Unfold the bounces: the ball travels a sawtooth with period `2 * height`.
+    let travelled = (position.y - min_y + velocity.y * time).rem_euclid(2.0 * height);
+    Some(if travelled > height {
+        max_y - (travelled - height)
+    } else {
+        min_y + travelled
+    })
+}
+
+pub(crate) fn drive_ai_paddles(
+    time: Res<Time>,
+    mut rng: ResMut<PongRng>,
+    mut paddles: Query<(&mut AiController, &mut Paddle, &Position, &Shape), Without<Ball>>,
+    balls: Query<(&Position, &Velocity, &Shape), With<Ball>>,
+) {
+    for (mut controller, mut paddle, position, shape) in &mut paddles {
+        if controller.reaction.tick(time.delta()).just_finished() {
+            // Follow the nearest ball coming this way, or drift back to the
+            // middle.
+            let incoming = balls
+                .iter()
+                .filter(|(ball, velocity, _)| (position.0.x - ball.0.x) * velocity.0.x > 0.0)
+                .min_by(|(a, ..), (b, ..)| {
+                    let a = (position.0.x - a.0.x).abs();
+                    let b = (position.0.x - b.0.x).abs();
+                    a.total_cmp(&b)
+                });
+
+            controller.target = match incoming {
+                Some((ball, velocity, ball_shape)) if controller.profile.predict_bounces => {
+                    let reach = (shape.0.x + ball_shape.0.x) / 2.0;
+                    let face = position.0.x - reach * velocity.0.x.signum();
+                    let half_height = ball_shape.0.y / 2.0;
+                    predict_intercept(ball.0, velocity.0, face, half_height, 100.0 - half_height)
+                        .unwrap_or(ball.0.y)
+                }
+                Some((ball, ..)) => ball.0.y,
+                None => 50.0,
+            };
+            if incoming.is_none() {
+                controller.aim_offset = None;
+            } else {
+                let error = controller.profile.aim_error;
+                let offset = *controller
+                    .aim_offset
+                    .get_or_insert_with(|| rng.0.random_range(-error..=error));
+                controller.target += offset;
+            }
+        }
+
+        let offset = controller.target - position.0.y;
+        paddle.input_direction.0.y = if offset.abs() < DEAD_ZONE {
+            0.0
+        } else {
+            (offset / SLOWDOWN_DISTANCE).clamp(-1.0, 1.0) * controller.profile.max_speed
+        };
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::{
+    ecs::{component::HookContext, world::DeferredWorld},
+    prelude::*,
+};
+
+use super::TEXT;
+
+#[cfg(feature = "net")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Default)]
+#[require(Position,
+    Shape = Shape(Vec2 { x: 4.0, y: 20.0 }),
+    Velocity,
+    Speed = Speed(80.0),
+    Drag = Drag(0.05),
+    BoxCollider = BoxCollider {kinematic: true, ..default()},
+    Sprite = Sprite::from_color(TEXT, Vec2 { x: 1.0, y: 1.0 })
+)]
+pub struct Paddle {
+    pub input_direction: InputDirection,
+    pub player: u8,
+}
+
+#[derive(Component)]
+#[cfg_attr(feature = "net", derive(Serialize, Deserialize))]
+#[require(
+    Name = Name::new("Ball"),
+    Position = Position(Vec2 { x: 50.0, y: 50.0 }),
+    Shape = Shape(Vec2 { x: 4.0, y: 4.0 }),
+    Velocity,
+    Speed = Speed(40.0),
+    BoxCollider = BoxCollider {kinematic: false, friction: 0.5},
+    Sprite = Sprite::from_color(TEXT, Vec2 { x: 1.0, y: 1.0 }),
+)]
+pub struct Ball;
+
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "net", derive(Serialize, Deserialize))]
+#[require(Transform)]
+pub struct Velocity(pub Vec2);
+
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct InputDirection(pub Vec2);
+
+#[derive(Component, Default)]
+#[cfg_attr(feature = "net", derive(Serialize, Deserialize))]
+#[require(Transform)]
+pub struct Speed(pub f32);
+
+/// Fraction of velocity lost every 1/60th of a second
+#[derive(Component, Default)]
+#[require(Transform, Velocity)]
+pub struct Drag(pub f32);
+
+#[derive(Component)]
+#[require(Transform, Position, Velocity, Shape)]
+pub struct BoxCollider {
+    pub kinematic: bool,
+    pub friction: f32,
+}
+
+impl Default for BoxCollider {
+    fn default() -> BoxCollider {
+        BoxCollider {
+            kinematic: false,
+            friction: 0.1,
+        }
+    }
+}
+
+/// Position in screen space
+/// (0.0, 0.0) = Bottom left
+/// (100.0, 100.0) = Top Right
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "net", derive(Serialize, Deserialize))]
+#[require(Transform, PreviousPosition)]
+#[component(on_add = sync_previous_position)]
+pub struct Position(pub Vec2);
+
+/// [`Position`] at the start of the current fixed tick, used to interpolate
+/// [`Transform`] between ticks
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct PreviousPosition(pub Vec2);
+
+/// Starts interpolation from the spawn position rather than the origin.
+fn sync_previous_position(mut world: DeferredWorld, context: HookContext) {
+    let Some(position) = world.get::<Position>(context.entity).copied() else {
+        return;
+    };
+    if let Some(mut previous) = world.get_mut::<PreviousPosition>(context.entity) {
+        previous.0 = position.0;
+    }
+}
+
+/// Shape as % of screen in the x and y axis
+/// (100, 100) is a rect that fills the window exactly
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+#[require(Transform)]
+pub struct Shape(pub Vec2);
+
+#[derive(Component)]
+pub struct Player;
+
+/// Paddle moved from outside the plugin, e.g. by the inputs of a network
+/// client. Local input, the AI and paddle physics leave it alone, its
+/// [`Velocity`] is only read to put spin on the ball.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct RemoteControlled;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "net", derive(Serialize, Deserialize))]
+pub enum Scorer {
+    Player,
+    Ai,
+}
+
+#[derive(Component, Default)]
+#[require(
+    Text = Text::new("0"),
+    TextColor = TextColor(TEXT),
+    TextFont = TextFont {font_size: 60.0, ..default() },
+    TextLayout = TextLayout::new_with_justify(JustifyText::Center),
+    Scorer = Scorer::Player,
+)]
+pub struct ScoreCard;
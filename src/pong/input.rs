@@ -0,0 +1,248 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use clap::ValueEnum;
+
+use super::{
+    GameState, PongConfig, PongRng,
+    ai::AiController,
+    components::{Paddle, RemoteControlled},
+    spawn_ball,
+};
+use crate::stick::StickSettings;
+
+/// Who controls each paddle.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Player 1 on the left against the computer
+    #[default]
+    VsAi,
+    /// Two local players
+    TwoPlayer,
+    /// The computer plays itself
+    AiVsAi,
+}
+
+impl GameMode {
+    /// Player slots controlled by people in this mode
+    pub fn human_players(self) -> &'static [u8] {
+        match self {
+            GameMode::VsAi => &[1],
+            GameMode::TwoPlayer => &[1, 2],
+            GameMode::AiVsAi => &[],
+        }
+    }
+}
+
+/// Something a player can do, independent of the key or button bound to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    Pause,
+    /// Serve straight away instead of waiting out the countdown
+    Serve,
+}
+
+/// Keys, buttons and stick a single player slot is controlled with.
+#[derive(Clone, Debug, Default)]
+pub struct PlayerBindings {
+    pub keys: Vec<(KeyCode, Action)>,
+    /// Buttons on the gamepad assigned to this slot, see [`GamepadSlot`]
+    pub buttons: Vec<(GamepadButton, Action)>,
+    /// Stick axis on the assigned gamepad that moves the paddle, up being
+    /// positive
+    pub axis: Option<GamepadAxis>,
+    /// Deadzones and response curve applied to [`Self::axis`]
+    pub stick: StickSettings,
+}
+
+impl PlayerBindings {
+    /// Moves with `up` and `down`, plus the default gamepad layout
+    pub fn keys(up: KeyCode, down: KeyCode, serve: KeyCode) -> Self {
+        Self {
+            keys: vec![
+                (up, Action::MoveUp),
+                (down, Action::MoveDown),
+                (KeyCode::Escape, Action::Pause),
+                (serve, Action::Serve),
+            ],
+            buttons: vec![
+                (GamepadButton::DPadUp, Action::MoveUp),
+                (GamepadButton::DPadDown, Action::MoveDown),
+                (GamepadButton::Start, Action::Pause),
+                (GamepadButton::South, Action::Serve),
+            ],
+            axis: Some(GamepadAxis::LeftStickY),
+            stick: StickSettings::default(),
+        }
+    }
+
+    fn keys_for(&self, action: Action) -> impl Iterator<Item = KeyCode> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+    }
+
+    fn buttons_for(&self, action: Action) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.buttons
+            .iter()
+            .filter(move |(_, bound)| *bound == action)
+            .map(|(button, _)| *button)
+    }
+}
+
+/// Bindings for every player slot, keyed by [`Paddle::player`].
+#[derive(Resource, Clone, Debug, Default)]
+pub struct InputBindings {
+    pub players: HashMap<u8, PlayerBindings>,
+}
+
+impl InputBindings {
+    /// Default bindings for `mode`: the arrow keys move player 1 when playing
+    /// alone, and player 2 when the keyboard is shared.
+    pub fn for_mode(mode: GameMode) -> Self {
+        let mut players = HashMap::default();
+        match mode {
+            GameMode::VsAi | GameMode::AiVsAi => {
+                let mut bindings =
+                    PlayerBindings::keys(KeyCode::KeyW, KeyCode::KeyS, KeyCode::Space);
+                bindings.keys.push((KeyCode::ArrowUp, Action::MoveUp));
+                bindings.keys.push((KeyCode::ArrowDown, Action::MoveDown));
+                players.insert(1, bindings);
+            }
+            GameMode::TwoPlayer => {
+                players.insert(
+                    1,
+                    PlayerBindings::keys(KeyCode::KeyW, KeyCode::KeyS, KeyCode::Space),
+                );
+                players.insert(
+                    2,
+                    PlayerBindings::keys(KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::Enter),
+                );
+            }
+        }
+        Self { players }
+    }
+}
+
+/// Player slot a connected gamepad controls.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GamepadSlot(pub u8);
+
+/// Sent when a player asks to serve before the countdown is over.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ServeRequested;
+
+/// Hands newly connected gamepads the first free human slot, and frees the
+/// slot of gamepads that disconnect.
+pub(crate) fn assign_gamepads(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    unassigned: Query<Entity, (With<Gamepad>, Without<GamepadSlot>)>,
+    assigned: Query<&GamepadSlot, With<Gamepad>>,
+    mut disconnected: RemovedComponents<Gamepad>,
+) {
+    for entity in disconnected.read() {
+        if let Ok(mut entity) = commands.get_entity(entity) {
+            entity.remove::<GamepadSlot>();
+        }
+    }
+
+    let mut taken: Vec<u8> = assigned.iter().map(|slot| slot.0).collect();
+    for entity in &unassigned {
+        let Some(&player) = config
+            .mode
+            .human_players()
+            .iter()
+            .find(|player| !taken.contains(player))
+        else {
+            break;
+        };
+        info!("Gamepad {entity} controls player {player}");
+        commands.entity(entity).insert(GamepadSlot(player));
+        taken.push(player);
+    }
+}
+
+pub(crate) fn handle_player_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<(&Gamepad, &GamepadSlot)>,
+    bindings: Res<InputBindings>,
+    mut paddles: Query<&mut Paddle, (Without<AiController>, Without<RemoteControlled>)>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut serve: EventWriter<ServeRequested>,
+) {
+    let pressed = |player: u8, bindings: &PlayerBindings, action: Action| {
+        keyboard_input.any_pressed(bindings.keys_for(action))
+            || gamepads
+                .iter()
+                .filter(|(_, slot)| slot.0 == player)
+                .any(|(gamepad, _)| bindings.buttons_for(action).any(|b| gamepad.pressed(b)))
+    };
+    let just_pressed = |player: u8, bindings: &PlayerBindings, action: Action| {
+        keyboard_input.any_just_pressed(bindings.keys_for(action))
+            || gamepads
+                .iter()
+                .filter(|(_, slot)| slot.0 == player)
+                .any(|(gamepad, _)| {
+                    bindings
+                        .buttons_for(action)
+                        .any(|b| gamepad.just_pressed(b))
+                })
+    };
+
+    if bindings
+        .players
+        .iter()
+        .any(|(&player, bindings)| just_pressed(player, bindings, Action::Pause))
+    {
+        match game_state.get() {
+            GameState::Playing => next_state.set(GameState::Paused),
+            GameState::Paused => next_state.set(GameState::Playing),
+        };
+    }
+    if bindings
+        .players
+        .iter()
+        .any(|(&player, bindings)| just_pressed(player, bindings, Action::Serve))
+    {
+        serve.write(ServeRequested);
+    }
+
+    for mut paddle in &mut paddles {
+        let player = paddle.player;
+        let Some(bindings) = bindings.players.get(&player) else {
+            paddle.input_direction.0 = Vec2::ZERO;
+            continue;
+        };
+        let stick = bindings.axis.map_or(0.0, |axis| {
+            gamepads
+                .iter()
+                .filter(|(_, slot)| slot.0 == player)
+                .filter_map(|(gamepad, _)| gamepad.get(axis))
+                .map(|value| bindings.stick.axis(value))
+                .find(|value| *value != 0.0)
+                .unwrap_or(0.0)
+        });
+
+        let mut direction = stick;
+        if pressed(player, bindings, Action::MoveUp) {
+            direction = 1.0;
+        } else if pressed(player, bindings, Action::MoveDown) {
+            direction = -1.0;
+        }
+        paddle.input_direction.0 = Vec2::new(0.0, direction);
+    }
+}
+
+pub(crate) fn handle_debug_keys(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    config: Res<PongConfig>,
+    mut rng: ResMut<PongRng>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        spawn_ball(&mut commands, &config, &mut rng);
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use super::{
+    GamePhase, GameState, PongConfig, RestartMatch, Score, TEXT, components::Scorer,
+    input::GameMode, rules::MatchWinner,
+};
+
+const OVERLAY: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const BUTTON: Color = Color::srgb_u8(49, 50, 68);
+const BUTTON_HOVERED: Color = Color::srgb_u8(69, 71, 90);
+
+/// Buttons on the pause menu and the results screen.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuButton {
+    Resume,
+    Restart,
+    Rematch,
+    Quit,
+}
+
+impl MenuButton {
+    fn label(self) -> &'static str {
+        match self {
+            MenuButton::Resume => "Resume",
+            MenuButton::Restart => "Restart",
+            MenuButton::Rematch => "Rematch",
+            MenuButton::Quit => "Quit",
+        }
+    }
+}
+
+pub(crate) fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub(crate) fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+pub(crate) fn spawn_pause_menu(commands: Commands) {
+    spawn_menu(
+        commands,
+        StateScoped(GameState::Paused),
+        "Paused".to_string(),
+        &[MenuButton::Resume, MenuButton::Restart, MenuButton::Quit],
+    );
+}
+
+pub(crate) fn spawn_results_screen(
+    commands: Commands,
+    winner: Res<MatchWinner>,
+    score: Res<Score>,
+    config: Res<PongConfig>,
+) {
+    let winner = match (config.mode, winner.0) {
+        (GameMode::VsAi, Scorer::Player) => "Player",
+        (GameMode::VsAi, Scorer::Ai) => "Computer",
+        (GameMode::TwoPlayer, Scorer::Player) => "Player 1",
+        (GameMode::TwoPlayer, Scorer::Ai) => "Player 2",
+        (GameMode::AiVsAi, Scorer::Player) => "Left",
+        (GameMode::AiVsAi, Scorer::Ai) => "Right",
+    };
+    // Without the simulation the match is run elsewhere, and so is a rematch
+    let buttons: &[MenuButton] = if config.simulate {
+        &[MenuButton::Rematch, MenuButton::Quit]
+    } else {
+        &[MenuButton::Quit]
+    };
+    spawn_menu(
+        commands,
+        StateScoped(GamePhase::GameOver),
+        format!("{winner} wins\n{} - {}", score.player, score.ai),
+        buttons,
+    );
+}
+
+fn spawn_menu(mut commands: Commands, scope: impl Bundle, title: String, buttons: &[MenuButton]) {
+    commands
+        .spawn((
+            Name::new("Menu"),
+            scope,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(OVERLAY),
+            GlobalZIndex(1),
+        ))
+        .with_children(|menu| {
+            menu.spawn((
+                Text::new(title),
+                TextColor(TEXT),
+                TextLayout::new_with_justify(JustifyText::Center),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+            ));
+            for &button in buttons {
+                menu.spawn((
+                    button,
+                    Button,
+                    Node {
+                        width: Val::Px(240.0),
+                        padding: UiRect::all(Val::Px(12.0)),
+                        justify_content: JustifyContent::Center,
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    BackgroundColor(BUTTON),
+                    BorderColor(TEXT),
+                    BorderRadius::all(Val::Px(8.0)),
+                ))
+                .with_child((
+                    Text::new(button.label()),
+                    TextColor(TEXT),
+                    TextFont {
+                        font_size: 30.0,
+                        ..default()
+                    },
+                ));
+            }
+        });
+}
+
+pub(crate) fn handle_menu_buttons(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut restart: EventWriter<RestartMatch>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            MenuButton::Resume => next_state.set(GameState::Playing),
+            MenuButton::Restart | MenuButton::Rematch => {
+                restart.write(RestartMatch);
+            }
+            MenuButton::Quit => {
+                exit.write(AppExit::Success);
+            }
+        }
+    }
+}
+
+/// Menu buttons the pointer just moved onto, off or pressed.
+type ChangedButton = (Changed<Interaction>, With<MenuButton>);
+
+pub(crate) fn highlight_menu_buttons(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), ChangedButton>,
+) {
+    for (interaction, mut background) in &mut buttons {
+        background.0 = match interaction {
+            Interaction::Pressed | Interaction::Hovered => BUTTON_HOVERED,
+            Interaction::None => BUTTON,
+        };
+    }
+}
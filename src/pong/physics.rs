@@ -0,0 +1,234 @@
+use bevy::{
+    math::bounding::{Aabb2d, BoundingVolume},
+    prelude::*,
+};
+
+use super::components::{
+    Ball, BoxCollider, Drag, Paddle, Position, PreviousPosition, RemoteControlled, Shape, Speed,
+    Velocity,
+};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Collision {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Everything [`apply_velocity`] moves.
+type SelfMoving = (Without<Ball>, Without<RemoteControlled>);
+
+/// Moves everything except the ball, which is moved by [`handle_collisions`],
+/// and remote paddles, which are moved by whoever controls them.
+pub(crate) fn apply_velocity(
+    time: Res<Time>,
+    mut query: Query<(&Velocity, &mut Position), SelfMoving>,
+) {
+    for (velocity, mut position) in query.iter_mut() {
+        position.0 += velocity.0 * time.delta_secs();
+    }
+}
+
+/// Rate [`Drag`] is expressed in, so drag feels the same at any tick rate.
+const DRAG_REFERENCE_RATE: f32 = 60.0;
+
+pub(crate) fn apply_drag(time: Res<Time>, mut query: Query<(&mut Velocity, &Drag)>) {
+    for (mut velocity, drag) in query.iter_mut() {
+        if drag.0 == 0.0 {
+            continue;
+        }
+        let retained = (1.0 - drag.0.clamp(0.0, 1.0)).powf(time.delta_secs() * DRAG_REFERENCE_RATE);
+        velocity.0 *= retained;
+    }
+}
+
+pub(crate) fn store_previous_positions(mut query: Query<(&Position, &mut PreviousPosition)>) {
+    for (position, mut previous) in &mut query {
+        previous.0 = position.0;
+    }
+}
+
+pub(crate) fn apply_paddle_input(
+    mut query: Query<(&Paddle, &Speed, &Position, &mut Velocity), Without<RemoteControlled>>,
+) {
+    for (paddle, speed, position, mut velocity) in &mut query {
+        if paddle.input_direction.0.y == 0.0 {
+            continue;
+        }
+
+        velocity.0.y = paddle.input_direction.0.y * speed.0;
+
+        if (position.0.y > 88.0 && velocity.0.y > 0.0)
+            || (position.0.y < 12.0 && velocity.0.y < 0.0)
+        {
+            velocity.0.y = 0.0;
+        }
+    }
+}
+
+/// A contact found by [`sweep`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SweptCollision {
+    /// Fraction of the displacement travelled before touching, in `0.0..=1.0`
+    pub time: f32,
+    /// Side of the other box that was hit
+    pub side: Collision,
+    /// How far the boxes already overlapped at the start of the sweep
+    pub depth: f32,
+}
+
+impl Collision {
+    /// Unit vector pointing out of the side that was hit
+    pub fn normal(self) -> Vec2 {
+        match self {
+            Collision::Top => Vec2::Y,
+            Collision::Bottom => Vec2::NEG_Y,
+            Collision::Left => Vec2::NEG_X,
+            Collision::Right => Vec2::X,
+        }
+    }
+
+    fn from_axis(axis: usize, sign: f32) -> Self {
+        match (axis, sign > 0.0) {
+            (0, true) => Collision::Right,
+            (0, false) => Collision::Left,
+            (_, true) => Collision::Top,
+            (_, false) => Collision::Bottom,
+        }
+    }
+}
+
+/// Sweeps `this` along `displacement` and returns the first contact with
+/// `other`, which is treated as static.
+///
+/// Boxes that already overlap report a contact at `time == 0.0` on the side
+/// with the least penetration, along with the penetration `depth`.
+pub fn sweep(this: Aabb2d, displacement: Vec2, other: Aabb2d) -> Option<SweptCollision> {
+    // Grow `other` by the half size of `this` so the sweep becomes a ray cast
+    // from the center of `this`.
+    let half_size = this.half_size();
+    let min = other.min - half_size;
+    let max = other.max + half_size;
+    let origin = this.center();
+
+    if origin.cmpgt(min).all() && origin.cmplt(max).all() {
+        let penetration = [
+            (max.x - origin.x, 0, 1.0),
+            (origin.x - min.x, 0, -1.0),
+            (max.y - origin.y, 1, 1.0),
+            (origin.y - min.y, 1, -1.0),
+        ];
+        let (depth, axis, sign) = penetration
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        return Some(SweptCollision {
+            time: 0.0,
+            side: Collision::from_axis(axis, sign),
+            depth,
+        });
+    }
+
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut side = None;
+    for axis in 0..2 {
+        let (origin, delta, min, max) = (origin[axis], displacement[axis], min[axis], max[axis]);
+        if delta == 0.0 {
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+        let (near, far) = if delta > 0.0 {
+            ((min - origin) / delta, (max - origin) / delta)
+        } else {
+            ((max - origin) / delta, (min - origin) / delta)
+        };
+        if near > entry {
+            entry = near;
+            side = Some(Collision::from_axis(axis, -delta));
+        }
+        exit = exit.min(far);
+    }
+
+    if entry > exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+    side.map(|side| SweptCollision {
+        time: entry,
+        side,
+        depth: 0.0,
+    })
+}
+
+/// How many contacts the ball can resolve in a single step, e.g. when it
+/// clips a barrier and a paddle in the same frame. Any time left after that
+/// is dropped, as moving the ball through it unswept could tunnel.
+const MAX_CONTACTS_PER_STEP: usize = 4;
+
+/// Gap left between the ball and a collider after resolving a contact.
+const CONTACT_SKIN: f32 = 0.001;
+
+/// Colliders the ball bounces off.
+type Obstacle = (With<BoxCollider>, Without<Ball>);
+
+/// Moves the ball along its velocity, stopping at the first collider in its
+/// path and reflecting off it.
+///
+/// The ball is swept rather than teleported so it can not tunnel through
+/// paddles or barriers at high speed, and it only bounces while moving into
+/// a collider so a resting contact can not flip its velocity every frame.
+pub(crate) fn handle_collisions(
+    time: Res<Time>,
+    mut ball: Query<(&mut Velocity, &mut Position, &Shape), With<Ball>>,
+    other_things: Query<(&Position, &Velocity, &Shape), Obstacle>,
+) {
+    for (mut ball_velocity, mut ball_position, ball_shape) in &mut ball {
+        let mut remaining = time.delta_secs();
+        for _ in 0..MAX_CONTACTS_PER_STEP {
+            let displacement = ball_velocity.0 * remaining;
+            let ball_box = Aabb2d::new(ball_position.0, ball_shape.0 / 2.0);
+            let Some((contact, velocity)) = other_things
+                .iter()
+                .filter_map(|(position, velocity, shape)| {
+                    sweep(
+                        ball_box,
+                        displacement,
+                        Aabb2d::new(position.0, shape.0 / 2.0),
+                    )
+                    .map(|contact| (contact, velocity))
+                })
+                .min_by(|(a, _), (b, _)| a.time.total_cmp(&b.time))
+            else {
+                ball_position.0 += displacement;
+                break;
+            };
+
+            let normal = contact.side.normal();
+            ball_position.0 +=
+                displacement * contact.time + normal * (contact.depth + CONTACT_SKIN);
+            remaining -= remaining * contact.time;
+
+            if ball_velocity.0.dot(normal) < 0.0 {
+                match contact.side {
+                    Collision::Top | Collision::Bottom => ball_velocity.0.y *= -1.0,
+                    Collision::Left | Collision::Right => {
+                        ball_velocity.0.x *= -1.0;
+                        ball_velocity.0.y += velocity.0.y * 0.3;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn kill_offscreen(mut commands: Commands, query: Query<(Entity, &Position)>) {
+    for (entity, position) in query {
+        // Kill entity if it is over 100 units from the center of the screen
+        if position.0.distance(Vec2 { x: 50.0, y: 50.0 }) > 80.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+
+use super::components::{BoxCollider, Position, PreviousPosition, Shape};
+
+/// Resolution the playfield is mapped to when there is no window, e.g. when
+/// running under `MinimalPlugins`.
+pub const HEADLESS_RESOLUTION: Vec2 = Vec2::new(1000.0, 1000.0);
+
+fn resolution(window: Option<Single<&Window>>) -> Vec2 {
+    window.map_or(HEADLESS_RESOLUTION, |window| window.size())
+}
+
+/// Places entities between their last two fixed tick positions so movement is
+/// smooth at any frame rate.
+pub(crate) fn position_translation(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&Position, &PreviousPosition, &mut Transform)>,
+    window: Option<Single<&Window>>,
+) {
+    let resolution = resolution(window);
+    let alpha = fixed_time.overstep_fraction();
+    for (position, previous, mut transform) in query.iter_mut() {
+        let position = previous.0.lerp(position.0, alpha);
+        transform.translation.x = position.x * 0.01 * resolution.x - (resolution.x / 2.0);
+        transform.translation.y = position.y * 0.01 * resolution.y - (resolution.y / 2.0);
+    }
+}
+
+pub(crate) fn scale_to_window(
+    mut query: Query<(&Shape, &mut Transform)>,
+    window: Option<Single<&Window>>,
+) {
+    let resolution = resolution(window);
+    for (scale, mut transform) in query.iter_mut() {
+        transform.scale.x = scale.0.x * 0.01 * resolution.x;
+        transform.scale.y = scale.0.y * 0.01 * resolution.y;
+    }
+}
+
+pub(crate) fn draw_box_collider_gizmos(
+    mut gizmos: Gizmos,
+    query: Query<(&Transform, &BoxCollider)>,
+) {
+    for (transform, collider) in query {
+        gizmos.rect_2d(
+            Isometry2d::from_translation(transform.translation.truncate()),
+            transform.scale.truncate(),
+            if collider.kinematic {
+                Color::srgb(1.0, 0.0, 0.0)
+            } else {
+                Color::srgb(0.0, 0.0, 1.0)
+            },
+        );
+    }
+}
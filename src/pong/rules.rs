@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use clap::{ArgAction, Args, ValueEnum};
+
+use super::{
+    GamePhase, PongConfig, PongRng, Score, ScoredEvent,
+    components::{Ball, Scorer, Velocity},
+    input::ServeRequested,
+    serve_velocity,
+};
+
+/// Who gets the ball after a point.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ServeRule {
+    /// Serve towards the side that just conceded
+    #[default]
+    Loser,
+    /// Serve towards each side in turn
+    Alternate,
+}
+
+/// How a match is won, settable from the command line by flattening it into
+/// a `clap` parser.
+#[derive(Resource, Args, Clone, Debug, PartialEq)]
+pub struct MatchRules {
+    /// Points needed to win the match
+    #[arg(long, default_value_t = 11)]
+    pub points_to_win: u32,
+    /// Whether the winner needs a two point lead
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    pub win_by_two: bool,
+    /// Who receives the serve after a point
+    #[arg(long, value_enum, default_value_t)]
+    pub serve: ServeRule,
+    /// Seconds of play after which the leader wins, or the next point wins
+    /// if the score is tied
+    #[arg(long)]
+    pub time_limit: Option<f32>,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            points_to_win: 11,
+            win_by_two: true,
+            serve: ServeRule::default(),
+            time_limit: None,
+        }
+    }
+}
+
+impl MatchRules {
+    /// Winner of a match at `score` after `elapsed` seconds of play, if any
+    pub fn winner(&self, score: &Score, elapsed: f32) -> Option<Scorer> {
+        let (leader, lead, points) = match score.player.cmp(&score.ai) {
+            std::cmp::Ordering::Greater => (Scorer::Player, score.player - score.ai, score.player),
+            std::cmp::Ordering::Less => (Scorer::Ai, score.ai - score.player, score.ai),
+            std::cmp::Ordering::Equal => return None,
+        };
+
+        let reached_target = points >= self.points_to_win && (!self.win_by_two || lead >= 2);
+        let out_of_time = self.time_limit.is_some_and(|limit| elapsed >= limit);
+        (reached_target || out_of_time).then_some(leader)
+    }
+}
+
+/// Seconds of rally played this match, for [`MatchRules::time_limit`].
+#[derive(Resource, Default, Debug)]
+pub struct MatchClock(pub f32);
+
+/// Side that won the last match, present while in [`GamePhase::GameOver`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchWinner(pub Scorer);
+
+/// Direction of the next serve along x, decided when a point is scored.
+#[derive(Resource, Default)]
+pub(crate) struct NextServe(pub f32);
+
+/// Counts down the pause between a point and the next serve.
+#[derive(Resource)]
+pub(crate) struct ScoringTimer(pub Timer);
+
+pub(crate) fn tick_match_clock(time: Res<Time>, mut clock: ResMut<MatchClock>) {
+    clock.0 += time.delta_secs();
+}
+
+pub(crate) fn apply_match_rules(
+    mut commands: Commands,
+    mut events: EventReader<ScoredEvent>,
+    rules: Res<MatchRules>,
+    score: Res<Score>,
+    clock: Res<MatchClock>,
+    mut next_serve: ResMut<NextServe>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    let mut scored = false;
+    for event in events.read() {
+        scored = true;
+        next_serve.0 = match (rules.serve, event.0) {
+            (ServeRule::Loser, Scorer::Player) => 1.0,
+            (ServeRule::Loser, Scorer::Ai) => -1.0,
+            (ServeRule::Alternate, _) if next_serve.0 > 0.0 => -1.0,
+            (ServeRule::Alternate, _) => 1.0,
+        };
+    }
+
+    if let Some(winner) = rules.winner(&score, clock.0) {
+        commands.insert_resource(MatchWinner(winner));
+        next_phase.set(GamePhase::GameOver);
+    } else if scored {
+        next_phase.set(GamePhase::Scoring);
+    }
+}
+
+pub(crate) fn reset_scoring_timer(mut timer: ResMut<ScoringTimer>) {
+    timer.0.reset();
+}
+
+/// Serves the ball once the pause after a point is over.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn finish_scoring(
+    time: Res<Time>,
+    mut timer: ResMut<ScoringTimer>,
+    config: Res<PongConfig>,
+    mut rng: ResMut<PongRng>,
+    next_serve: Res<NextServe>,
+    mut serve: EventReader<ServeRequested>,
+    mut balls: Query<&mut Velocity, With<Ball>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    let served_early = serve.read().count() > 0;
+    if !timer.0.tick(time.delta()).just_finished() && !served_early {
+        return;
+    }
+    for mut velocity in &mut balls {
+        velocity.0 = serve_velocity(next_serve.0, &config, &mut rng);
+    }
+    next_phase.set(GamePhase::Rally);
+}
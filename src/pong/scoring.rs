@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+
+use super::{
+    Score, ScoredEvent,
+    components::{Ball, Position, PreviousPosition, Scorer, Velocity},
+};
+
+pub(crate) fn detect_scoring(
+    ball: Single<&Position, With<Ball>>,
+    mut events: EventWriter<ScoredEvent>,
+) {
+    if ball.0.x > 100.0 {
+        events.write(ScoredEvent(Scorer::Player));
+    } else if ball.0.x < 0.0 {
+        events.write(ScoredEvent(Scorer::Ai));
+    }
+}
+
+/// Parks the ball in the middle until the next serve.
+pub(crate) fn reset_ball(
+    mut balls: Query<(&mut Position, &mut PreviousPosition, &mut Velocity), With<Ball>>,
+    mut events: EventReader<ScoredEvent>,
+) {
+    for _ in events.read() {
+        for (mut position, mut previous, mut velocity) in balls.iter_mut() {
+            position.0 = Vec2::new(50.0, 50.0);
+            previous.0 = position.0;
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+}
+
+pub(crate) fn update_score(mut score: ResMut<Score>, mut events: EventReader<ScoredEvent>) {
+    for event in events.read() {
+        match event.0 {
+            Scorer::Player => score.player += 1,
+            Scorer::Ai => score.ai += 1,
+        }
+    }
+}
+
+pub(crate) fn update_score_display(score: Res<Score>, mut query: Query<(&mut Text, &Scorer)>) {
+    if !score.is_changed() {
+        return;
+    }
+    for (mut text, scorer) in &mut query {
+        match scorer {
+            Scorer::Player => text.0 = score.player.to_string(),
+            Scorer::Ai => text.0 = score.ai.to_string(),
+        }
+    }
+}
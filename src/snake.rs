@@ -0,0 +1,445 @@
+//! Snake gameplay, packaged as a [`SnakePlugin`] that can be added to any app.
+//!
+//! The board is a grid of cells (see [`Grid`] and [`Position`]) mapped onto
+//! the primary window, or onto [`HEADLESS_RESOLUTION`] when there is none, so
+//! the plugin also runs under `MinimalPlugins`.
+//!
+//! Input is queued per snake (see [`InputQueue`]) and the snake moves one
+//! cell every [`FixedUpdate`] tick, taking at most one queued turn per tick.
+//! [`Food`] only ever spawns on free cells, and a snake that fills the board
+//! wins, see [`SnakeState::Won`].
+//!
+//! With [`SnakeConfig::players`] above one, up to four snakes share the board
+//! in a local versus game, each owning its body through [`SnakeSegments`] and
+//! steered by its own [`SnakeKeys`] or gamepad. The last snake alive wins.
+//!
+//! Any snake can be handed to the computer instead, see [`AiSnake`], and
+//! [`SnakeConfig::attract`] runs the game on its own as a demo.
+
+mod ai;
+mod board;
+pub mod components;
+mod food;
+mod hud;
+mod input;
+mod movement;
+mod render;
+mod score;
+
+use std::path::PathBuf;
+
+use bevy::{input::InputSystem, prelude::*};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+pub use board::{BoardArgs, BoardPreset, Edges, Grid};
+pub use components::*;
+pub use food::{Food, FoodKind};
+pub use hud::{Hud, ResultsScreen};
+pub use input::{GamepadSlot, SnakeKeys};
+pub use render::HEADLESS_RESOLUTION;
+pub use score::{HighScore, HighScores, RunClock, RunFinished, default_high_score_file};
+
+use crate::{apply_fixed_tick_transitions, stick::StickSettings};
+
+pub const BASE: Color = Color::srgb_u8(30, 30, 46);
+pub const TEXT: Color = Color::srgb_u8(205, 214, 244);
+pub const HEAD: Color = Color::srgb_u8(205, 214, 244);
+pub const BODY: Color = Color::srgb_u8(186, 194, 222);
+
+/// Head colours for players 2 to 4 in versus, player 1 keeps [`HEAD`].
+const VERSUS_HEADS: [Color; 3] = [
+    Color::srgb_u8(166, 227, 161),
+    Color::srgb_u8(203, 166, 247),
+    Color::srgb_u8(148, 226, 213),
+];
+
+/// Seconds the results stay up in attract mode before the next run.
+const ATTRACT_RESTART_DELAY: f32 = 3.0;
+
+/// How much a dead snake fades while it waits for the next run.
+const DEAD_ALPHA: f32 = 0.35;
+
+/// Head colour of a player's snake.
+pub fn player_color(player: u8) -> Color {
+    match player {
+        0 | 1 => HEAD,
+        player => VERSUS_HEADS[(player as usize - 2) % VERSUS_HEADS.len()],
+    }
+}
+
+fn body_color(player: u8) -> Color {
+    match player {
+        0 | 1 => BODY,
+        player => player_color(player).darker(0.1),
+    }
+}
+
+/// Tunables for a game of snake, inserted as a resource by [`SnakePlugin`].
+#[derive(Resource, Clone, Debug)]
+pub struct SnakeConfig {
+    /// Board size, edges and speed
+    pub grid: Grid,
+    /// Seed for starting directions and food placement, random when `None`
+    pub seed: Option<u64>,
+    /// Chance of a special [`FoodKind`] appearing with each normal food
+    pub special_food_chance: f64,
+    /// Where [`HighScores`] are saved, not saved at all when `None`.
+    /// [`default_high_score_file`] keeps them in the player's data folder.
+    pub high_score_file: Option<PathBuf>,
+    /// Snakes on the board, more than one starts a versus game that skips
+    /// the high score table
+    pub players: u8,
+    /// Keys for each player, [`SnakeKeys::for_players`] when `None`
+    pub keys: Option<SnakeKeys>,
+    /// Players whose snake is steered by the computer
+    pub ai_players: Vec<u8>,
+    /// Demo mode: the computer steers every snake, runs restart by
+    /// themselves and no high scores are kept
+    pub attract: bool,
+}
+
+impl Default for SnakeConfig {
+    fn default() -> Self {
+        Self {
+            grid: Grid::default(),
+            seed: None,
+            special_food_chance: 0.25,
+            high_score_file: None,
+            players: 1,
+            keys: None,
+            ai_players: Vec::new(),
+            attract: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SnakePlugin {
+    pub config: SnakeConfig,
+}
+
+/// Sent when a snake eats food.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GrowthEvent {
+    pub snake: Entity,
+    pub kind: FoodKind,
+}
+
+/// Sent when a snake's head crashes, carrying the [`SnakeHead`] entity.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SnakeDied(pub Entity);
+
+/// Player that won the last versus game, `None` for a draw.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnakeWinner(pub Option<u8>);
+
+/// Clears the board and starts a new run.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestartGame;
+
+impl Plugin for SnakePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone());
+        app.insert_resource(self.config.grid);
+        app.insert_resource(
+            self.config
+                .keys
+                .clone()
+                .unwrap_or_else(|| SnakeKeys::for_players(self.config.players)),
+        );
+        app.insert_resource(SnakeRng(match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        }));
+        app.add_systems(Startup, (setup, spawn_snakes, food::spawn_food).chain());
+        app.add_systems(Startup, (score::load_high_scores, hud::spawn_hud));
+        app.add_systems(
+            PreUpdate,
+            (
+                input::handle_keyboard_input,
+                (input::assign_gamepads, input::handle_gamepad_input).chain(),
+                input::handle_restart_input
+                    .run_if(in_state(SnakeState::GameOver).or(in_state(SnakeState::Won))),
+            )
+                .after(InputSystem),
+        );
+        app.add_systems(FixedFirst, apply_fixed_tick_transitions::<SnakeState>);
+        app.add_systems(
+            PostUpdate,
+            (render::position_translation, render::size_scaling),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                score::tick_run_clock,
+                movement::steer_snake,
+                ai::steer_ai,
+                movement::move_snake,
+                food::eat_food,
+                movement::grow_snake,
+                food::apply_speed_ups,
+                score::add_points,
+                food::expire_food,
+                resolve_deaths,
+                food::spawn_food,
+            )
+                .chain()
+                .run_if(in_state(SnakeState::Playing)),
+        );
+        app.add_systems(
+            OnEnter(SnakeState::Won),
+            (finish_won_run, hud::spawn_versus_results.run_if(versus)).chain(),
+        );
+        app.add_systems(
+            OnEnter(SnakeState::GameOver),
+            hud::spawn_versus_results.run_if(versus),
+        );
+        app.add_systems(
+            Update,
+            (
+                score::record_high_scores,
+                hud::spawn_results_screen,
+                hud::update_hud,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            (
+                restart_game,
+                food::spawn_food.run_if(on_event::<RestartGame>),
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            restart_attract
+                .run_if(attract.and(in_state(SnakeState::GameOver).or(in_state(SnakeState::Won)))),
+        );
+        app.insert_resource(Time::<Fixed>::from_hz(self.config.grid.tick_rate));
+        app.init_resource::<StickSettings>();
+        app.init_state::<SnakeState>();
+        app.enable_state_scoped_entities::<SnakeState>();
+        app.add_event::<GrowthEvent>();
+        app.add_event::<SnakeDied>();
+        app.add_event::<RunFinished>();
+        app.add_event::<RestartGame>();
+        app.init_resource::<RunClock>();
+        app.init_resource::<HighScores>();
+    }
+}
+
+#[derive(States, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum SnakeState {
+    #[default]
+    Playing,
+    /// The snake ran into itself or a wall, or at most one snake is left in
+    /// versus, waiting for [`RestartGame`]
+    GameOver,
+    /// The snake filled the whole board, waiting for [`RestartGame`]
+    Won,
+}
+
+/// Source of randomness for the snake and food, seeded from
+/// [`SnakeConfig::seed`].
+#[derive(Resource)]
+pub struct SnakeRng(pub StdRng);
+
+fn versus(config: Res<SnakeConfig>) -> bool {
+    config.players > 1
+}
+
+fn attract(config: Res<SnakeConfig>) -> bool {
+    config.attract
+}
+
+fn setup(mut commands: Commands) {
+    // Spawn Camera
+    commands.spawn(Camera2d);
+}
+
+/// Spawns one segment of a player's snake, coloured for that player.
+pub(crate) fn spawn_segment(commands: &mut Commands, player: u8, position: Position) -> Entity {
+    commands
+        .spawn((
+            SnakeSegment,
+            Sprite::from_color(body_color(player), Vec2::ONE),
+            Size::square(1.0),
+            position,
+        ))
+        .id()
+}
+
+fn spawn_snakes(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    config: Res<SnakeConfig>,
+    mut rng: ResMut<SnakeRng>,
+) {
+    respawn_snakes(&mut commands, &config, &grid, &mut rng);
+}
+
+/// Where each snake starts: a lone snake in the middle heading anywhere,
+/// versus snakes spread around the middle heading away from each other.
+fn starts(grid: &Grid, players: u8, rng: &mut SnakeRng) -> Vec<(Position, Direction)> {
+    if players <= 1 {
+        let direction = match rng.0.random_range(0..4) {
+            0 => Direction::Up,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            _ => Direction::Right,
+        };
+        return vec![(grid.center(), direction)];
+    }
+    let (width, height) = (grid.width, grid.height);
+    [
+        (width / 4, height / 2, Direction::Right),
+        (width * 3 / 4, height / 2, Direction::Left),
+        (width / 2, height * 3 / 4, Direction::Down),
+        (width / 2, height / 4, Direction::Up),
+    ]
+    .into_iter()
+    .take(players as usize)
+    .map(|(x, y, direction)| (Position { x, y }, direction))
+    .collect()
+}
+
+fn respawn_snakes(commands: &mut Commands, config: &SnakeConfig, grid: &Grid, rng: &mut SnakeRng) {
+    for (player, (position, direction)) in (1..).zip(starts(grid, config.players, rng)) {
+        let head = commands
+            .spawn((
+                SnakeHead { direction, player },
+                Transform::from_xyz(0.0, 0.0, -1.0),
+                Size::square(1.0),
+                position,
+                Sprite::from_color(player_color(player), Vec2::ONE),
+                SnakeSegment,
+            ))
+            .id();
+        commands.entity(head).insert(SnakeSegments(vec![head]));
+        if config.attract || config.ai_players.contains(&player) {
+            commands.entity(head).insert(AiSnake);
+        }
+    }
+}
+
+/// Ends the run when the snake dies alone, or when at most one snake is
+/// left standing in versus. Dead versus snakes fade but stay in the way.
+#[allow(clippy::too_many_arguments)]
+fn resolve_deaths(
+    mut reader: EventReader<SnakeDied>,
+    config: Res<SnakeConfig>,
+    clock: Res<RunClock>,
+    snakes: Query<(Entity, &SnakeHead, &SnakeScore, &SnakeSegments, Has<Dead>)>,
+    ai_snakes: Query<(), With<AiSnake>>,
+    mut sprites: Query<&mut Sprite>,
+    mut commands: Commands,
+    mut finished: EventWriter<RunFinished>,
+    mut next_state: ResMut<NextState<SnakeState>>,
+) {
+    let died: Vec<Entity> = reader.read().map(|SnakeDied(snake)| *snake).collect();
+    if died.is_empty() {
+        return;
+    }
+
+    if config.players <= 1 {
+        if let Ok((_, _, score, segments, _)) = snakes.get(died[0]) {
+            finished.write(RunFinished {
+                run: score::current_run(score, segments, &clock),
+                won: false,
+                ai: ai_snakes.contains(died[0]),
+            });
+        }
+        next_state.set(SnakeState::GameOver);
+        return;
+    }
+
+    for (_, _, _, segments, _) in snakes.iter_many(&died) {
+        for segment in &segments.0 {
+            if let Ok(mut sprite) = sprites.get_mut(*segment) {
+                let faded = sprite.color.alpha() * DEAD_ALPHA;
+                sprite.color.set_alpha(faded);
+            }
+        }
+    }
+
+    // `Dead` is only inserted once this tick's commands apply, so snakes that
+    // just died are filtered out by hand
+    let alive: Vec<u8> = snakes
+        .iter()
+        .filter(|(entity, .., dead)| !dead && !died.contains(entity))
+        .map(|(_, head, ..)| head.player)
+        .collect();
+    if alive.len() <= 1 {
+        commands.insert_resource(SnakeWinner(alive.first().copied()));
+        next_state.set(SnakeState::GameOver);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restart_game(
+    mut commands: Commands,
+    mut events: EventReader<RestartGame>,
+    food: Query<Entity, With<Food>>,
+    segments: Query<Entity, With<SnakeSegment>>,
+    grid: Res<Grid>,
+    config: Res<SnakeConfig>,
+    mut rng: ResMut<SnakeRng>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut clock: ResMut<RunClock>,
+    mut next_state: ResMut<NextState<SnakeState>>,
+) {
+    if events.read().last().is_none() {
+        return;
+    }
+
+    *clock = RunClock::default();
+    // Heads are segments too, so this clears every snake
+    for entity in food.iter().chain(segments.iter()) {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<SnakeWinner>();
+    fixed_time.set_timestep_hz(grid.tick_rate);
+    respawn_snakes(&mut commands, &config, &grid, &mut rng);
+    next_state.set(SnakeState::Playing);
+}
+
+/// Starts the next demo run once the results have been up for a while.
+fn restart_attract(time: Res<Time>, mut waited: Local<f32>, mut restart: EventWriter<RestartGame>) {
+    *waited += time.delta_secs();
+    if *waited >= ATTRACT_RESTART_DELAY {
+        *waited = 0.0;
+        restart.write(RestartGame);
+    }
+}
+
+/// Records a full board as a win for a lone snake, and in versus gives it
+/// to whoever scored the most, or calls a draw on a tie.
+fn finish_won_run(
+    config: Res<SnakeConfig>,
+    clock: Res<RunClock>,
+    snakes: Query<(&SnakeHead, &SnakeScore, &SnakeSegments, Has<AiSnake>)>,
+    mut commands: Commands,
+    mut finished: EventWriter<RunFinished>,
+) {
+    if config.players <= 1 {
+        for (_, score, segments, ai) in &snakes {
+            finished.write(RunFinished {
+                run: score::current_run(score, segments, &clock),
+                won: true,
+                ai,
+            });
+        }
+        return;
+    }
+
+    let best = snakes.iter().map(|(_, score, ..)| score.points).max();
+    let mut leaders = snakes
+        .iter()
+        .filter(|(_, score, ..)| Some(score.points) == best);
+    let winner = match (leaders.next(), leaders.next()) {
+        (Some((head, ..)), None) => Some(head.player),
+        _ => None,
+    };
+    commands.insert_resource(SnakeWinner(winner));
+}
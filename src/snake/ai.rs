@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::{
+    Food,
+    board::Grid,
+    components::{AiSnake, Dead, Direction, Position, SnakeHead, SnakeSegments},
+};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// Ticks until each cell of the board is free to move into.
+struct Occupancy {
+    grid: Grid,
+    free_in: Vec<usize>,
+}
+
+/// How a breadth first search first reached a cell.
+#[derive(Clone, Copy)]
+struct Reached {
+    steps: usize,
+    from: Position,
+}
+
+impl Occupancy {
+    /// Blocks every body on the board. A segment `n` cells from the end of a
+    /// live snake clears after `n` ticks, dead snakes never move.
+    fn new(grid: Grid, bodies: &[(&[Position], bool)]) -> Self {
+        let mut occupancy = Self {
+            grid,
+            free_in: vec![0; grid.width as usize * grid.height as usize],
+        };
+        for &(body, dead) in bodies {
+            occupancy.block(body, dead);
+        }
+        occupancy
+    }
+
+    fn block(&mut self, body: &[Position], dead: bool) {
+        for (i, position) in body.iter().enumerate() {
+            let index = self.index(*position);
+            let free_in = if dead { usize::MAX } else { body.len() - i };
+            self.free_in[index] = self.free_in[index].max(free_in);
+        }
+    }
+
+    fn index(&self, position: Position) -> usize {
+        position.y as usize * self.grid.width as usize + position.x as usize
+    }
+
+    fn free_at(&self, position: Position, step: usize) -> bool {
+        step >= self.free_in[self.index(position)]
+    }
+
+    /// Shortest way to every cell reachable from `start`, taking into account
+    /// that bodies move out of the way as the snake goes.
+    fn search(&self, start: Position) -> Vec<Option<Reached>> {
+        let mut reached = vec![None; self.free_in.len()];
+        reached[self.index(start)] = Some(Reached {
+            steps: 0,
+            from: start,
+        });
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((position, steps)) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                let Some(next) = self.grid.step(position, direction) else {
+                    continue;
+                };
+                let index = self.index(next);
+                if reached[index].is_none() && self.free_at(next, steps + 1) {
+                    reached[index] = Some(Reached {
+                        steps: steps + 1,
+                        from: position,
+                    });
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+        reached
+    }
+}
+
+/// Cells from the first step after `start` up to `target`.
+fn path(
+    occupancy: &Occupancy,
+    reached: &[Option<Reached>],
+    start: Position,
+    target: Position,
+) -> Vec<Position> {
+    let mut path = vec![target];
+    let mut position = target;
+    while let Some(step) = reached[occupancy.index(position)]
+        && step.from != start
+    {
+        position = step.from;
+        path.push(position);
+    }
+    path.reverse();
+    path
+}
+
+fn direction_to(grid: &Grid, from: Position, to: Position) -> Option<Direction> {
+    DIRECTIONS
+        .into_iter()
+        .find(|direction| grid.step(from, *direction) == Some(to))
+}
+
+/// Whether a snake laid over `body` can still reach its own tail, so it can
+/// always keep following it and never get boxed in.
+fn tail_reachable(grid: Grid, body: &[Position], others: &[(&[Position], bool)]) -> Option<usize> {
+    if body.len() <= 2 {
+        return Some(body.len() - 1);
+    }
+    let mut bodies = others.to_vec();
+    bodies.push((body, false));
+    let occupancy = Occupancy::new(grid, &bodies);
+    occupancy.search(body[0])[occupancy.index(*body.last().unwrap())].map(|tail| tail.steps)
+}
+
+/// Picks where a snake laid over `body`, head first, goes next.
+///
+/// It takes the shortest way to the nearest food that leaves its tail
+/// reachable once eaten. Without one it plays for time, following its tail
+/// the long way round, or heading for the most open space when even that is
+/// cut off.
+pub(crate) fn choose_direction(
+    grid: Grid,
+    body: &[Position],
+    travelling: Direction,
+    others: &[(&[Position], bool)],
+    food: &[Position],
+) -> Direction {
+    let head = body[0];
+    let mut bodies = others.to_vec();
+    bodies.push((body, false));
+    let occupancy = Occupancy::new(grid, &bodies);
+    let reached = occupancy.search(head);
+
+    let mut targets: Vec<(usize, Position)> = food
+        .iter()
+        .filter_map(|food| reached[occupancy.index(*food)].map(|to| (to.steps, *food)))
+        .filter(|(steps, _)| *steps > 0)
+        .collect();
+    targets.sort_by_key(|(steps, _)| *steps);
+    for (_, target) in targets {
+        let path = path(&occupancy, &reached, head, target);
+        // Body once the food is eaten, one longer than now
+        let after: Vec<Position> = path
+            .iter()
+            .rev()
+            .chain(body)
+            .copied()
+            .take(body.len() + 1)
+            .collect();
+        // A snake two long can path through the cell its neck is leaving, but
+        // can't turn back into it
+        let direction = direction_to(&grid, head, path[0]).unwrap_or(travelling);
+        if body.len() > 1 && direction == travelling.opposite() {
+            continue;
+        }
+        if tail_reachable(grid, &after, others).is_some() {
+            return direction;
+        }
+    }
+
+    let mut best = None;
+    for direction in DIRECTIONS {
+        if body.len() > 1 && direction == travelling.opposite() {
+            continue;
+        }
+        let Some(next) = grid.step(head, direction) else {
+            continue;
+        };
+        if !occupancy.free_at(next, 1) {
+            continue;
+        }
+        let after: Vec<Position> = std::iter::once(next)
+            .chain(body[..body.len() - 1].iter().copied())
+            .collect();
+        let score = match tail_reachable(grid, &after, others) {
+            Some(steps) => (1, steps),
+            None => {
+                let mut bodies = others.to_vec();
+                bodies.push((&after, false));
+                let occupancy = Occupancy::new(grid, &bodies);
+                (0, occupancy.search(next).iter().flatten().count())
+            }
+        };
+        if best.is_none_or(|(best, _)| score > best) {
+            best = Some((score, direction));
+        }
+    }
+    best.map_or(travelling, |(_, direction)| direction)
+}
+
+/// Heads of the [`AiSnake`]s still alive.
+type LiveAiHead = (With<AiSnake>, Without<Dead>);
+
+/// Steers every [`AiSnake`] for the coming tick.
+pub(crate) fn steer_ai(
+    grid: Res<Grid>,
+    mut ai_heads: Query<(Entity, &mut SnakeHead, &SnakeSegments), LiveAiHead>,
+    snakes: Query<(Entity, &SnakeSegments, Has<Dead>)>,
+    positions: Query<&Position>,
+    food: Query<&Position, With<Food>>,
+) {
+    let body = |segments: &SnakeSegments| -> Vec<Position> {
+        positions.iter_many(&segments.0).copied().collect()
+    };
+    let food: Vec<Position> = food.iter().copied().collect();
+    for (entity, mut head, segments) in &mut ai_heads {
+        let others: Vec<(Vec<Position>, bool)> = snakes
+            .iter()
+            .filter(|(other, ..)| *other != entity)
+            .map(|(_, segments, dead)| (body(segments), dead))
+            .collect();
+        let others: Vec<(&[Position], bool)> = others
+            .iter()
+            .map(|(body, dead)| (body.as_slice(), *dead))
+            .collect();
+        head.direction = choose_direction(*grid, &body(segments), head.direction, &others, &food);
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use clap::{Args, ValueEnum, value_parser};
+
+use super::components::{Direction, Position};
+
+/// What happens when the snake reaches the edge of the board.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Edges {
+    /// Come back in on the opposite side
+    #[default]
+    Wrap,
+    /// The edge is a wall the snake dies on
+    Solid,
+}
+
+/// The board the snake plays on, inserted as a resource by [`SnakePlugin`].
+///
+/// [`SnakePlugin`]: super::SnakePlugin
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    /// Columns, numbered from 0 on the left
+    pub width: i16,
+    /// Rows, numbered from 0 at the bottom
+    pub height: i16,
+    pub edges: Edges,
+    /// Cells the snake moves per second
+    pub tick_rate: f64,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        BoardPreset::default().grid()
+    }
+}
+
+impl Grid {
+    pub fn contains(&self, position: Position) -> bool {
+        (0..self.width).contains(&position.x) && (0..self.height).contains(&position.y)
+    }
+
+    /// Cell next to `position` in `direction`, or `None` when that is through
+    /// a solid edge.
+    pub fn step(&self, position: Position, direction: Direction) -> Option<Position> {
+        let mut next = position;
+        match direction {
+            Direction::Up => next.y += 1,
+            Direction::Down => next.y -= 1,
+            Direction::Left => next.x -= 1,
+            Direction::Right => next.x += 1,
+        };
+        match self.edges {
+            Edges::Wrap => Some(Position {
+                x: next.x.rem_euclid(self.width),
+                y: next.y.rem_euclid(self.height),
+            }),
+            Edges::Solid => self.contains(next).then_some(next),
+        }
+    }
+
+    /// Cell in the middle of the board
+    pub fn center(&self) -> Position {
+        Position {
+            x: self.width / 2,
+            y: self.height / 2,
+        }
+    }
+}
+
+/// Ready made boards.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BoardPreset {
+    /// Small wrapping board
+    #[default]
+    Classic,
+    /// Twice the size, and a little faster
+    Big,
+    /// Walls all around and a slower snake
+    Walled,
+}
+
+impl BoardPreset {
+    pub fn grid(self) -> Grid {
+        match self {
+            BoardPreset::Classic => Grid {
+                width: 12,
+                height: 12,
+                edges: Edges::Wrap,
+                tick_rate: 10.0,
+            },
+            BoardPreset::Big => Grid {
+                width: 24,
+                height: 24,
+                edges: Edges::Wrap,
+                tick_rate: 12.0,
+            },
+            BoardPreset::Walled => Grid {
+                width: 16,
+                height: 16,
+                edges: Edges::Solid,
+                tick_rate: 8.0,
+            },
+        }
+    }
+}
+
+/// Board selection for the command line: a preset with optional overrides,
+/// flattened into a `clap` parser.
+#[derive(Args, Clone, Debug, Default, PartialEq)]
+pub struct BoardArgs {
+    /// Board to start from
+    #[arg(short, long, value_enum, default_value_t)]
+    pub preset: BoardPreset,
+    /// Overrides the preset's number of columns
+    #[arg(long, value_parser = value_parser!(i16).range(1..))]
+    pub width: Option<i16>,
+    /// Overrides the preset's number of rows
+    #[arg(long, value_parser = value_parser!(i16).range(1..))]
+    pub height: Option<i16>,
+    /// Overrides the preset's edges
+    #[arg(long, value_enum)]
+    pub edges: Option<Edges>,
+    /// Overrides the preset's speed, in cells per second
+    #[arg(long, value_parser = parse_tick_rate)]
+    pub tick_rate: Option<f64>,
+}
+
+/// Only accepts speeds the fixed timestep can be set to.
+fn parse_tick_rate(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!(
+            "`{arg}` isn't a positive number of cells per second"
+        )),
+    }
+}
+
+impl BoardArgs {
+    pub fn grid(&self) -> Grid {
+        let preset = self.preset.grid();
+        Grid {
+            width: self.width.unwrap_or(preset.width),
+            height: self.height.unwrap_or(preset.height),
+            edges: self.edges.unwrap_or(preset.edges),
+            tick_rate: self.tick_rate.unwrap_or(preset.tick_rate),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use bevy::{math::CompassQuadrant, prelude::*};
+
+/// Head of a snake, which owns the rest of the body through
+/// [`SnakeSegments`].
+#[derive(Component, Debug)]
+#[require(InputQueue, SnakeSegments, LastTailPosition, SnakeScore)]
+pub struct SnakeHead {
+    /// Direction the snake moved in on the last tick
+    pub direction: Direction,
+    /// Player slot steering the snake, starting at 1
+    pub player: u8,
+}
+
+/// Every segment of a snake in order, head first.
+#[derive(Component, Default, Debug, Clone)]
+pub struct SnakeSegments(pub Vec<Entity>);
+
+/// Cell the tail left on the last tick, where the snake grows into.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct LastTailPosition(pub Option<Position>);
+
+/// Points a snake scored this run.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct SnakeScore {
+    /// Sum of [`FoodKind::points`] for everything eaten
+    ///
+    /// [`FoodKind::points`]: super::FoodKind::points
+    pub points: u32,
+}
+
+/// A snake steered by the computer rather than a player, see
+/// [`SnakeConfig::ai_players`].
+///
+/// [`SnakeConfig::ai_players`]: super::SnakeConfig::ai_players
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct AiSnake;
+
+/// A snake that crashed in a versus game. It stays on the board as an
+/// obstacle until the next run.
+#[derive(Component, Debug)]
+pub struct Dead;
+
+/// Most turns a snake remembers ahead of time, further presses are dropped.
+pub const MAX_QUEUED_TURNS: usize = 3;
+
+/// Turns requested for a snake that have not been applied yet, one is taken
+/// every tick so quick presses are neither lost nor merged.
+#[derive(Component, Debug, Default, Clone)]
+pub struct InputQueue(pub VecDeque<Direction>);
+
+impl InputQueue {
+    /// Queues a turn, ignoring repeats of the last queued turn and presses
+    /// past [`MAX_QUEUED_TURNS`].
+    pub fn push(&mut self, direction: Direction) {
+        if self.0.back() != Some(&direction) && self.0.len() < MAX_QUEUED_TURNS {
+            self.0.push_back(direction);
+        }
+    }
+
+    /// Takes the next turn that makes sense when `travelling` in a direction,
+    /// skipping turns that would reverse into the snake or change nothing.
+    pub fn next_turn(&mut self, travelling: Direction) -> Option<Direction> {
+        while let Some(direction) = self.0.pop_front() {
+            if direction != travelling && direction != travelling.opposite() {
+                return Some(direction);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Component)]
+pub struct SnakeSegment;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Self::Down,
+            Direction::Down => Self::Up,
+            Direction::Left => Self::Right,
+            Direction::Right => Self::Left,
+        }
+    }
+
+    pub fn from_quadrant(quadrant: CompassQuadrant) -> Self {
+        match quadrant {
+            CompassQuadrant::North => Self::Up,
+            CompassQuadrant::South => Self::Down,
+            CompassQuadrant::West => Self::Left,
+            CompassQuadrant::East => Self::Right,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Size {
+    pub fn square(x: f32) -> Self {
+        Self {
+            width: x,
+            height: x,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Component, Copy, Clone, Debug)]
+pub struct Position {
+    pub x: i16,
+    pub y: i16,
+}
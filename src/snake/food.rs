@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use rand::{Rng, seq::IndexedRandom};
+
+use super::{
+    GrowthEvent, SnakeConfig, SnakeRng, SnakeState,
+    board::Grid,
+    components::{Dead, Position, Size, SnakeHead, SnakeSegment},
+};
+
+pub const NORMAL: Color = Color::srgb_u8(243, 139, 168);
+pub const BONUS: Color = Color::srgb_u8(249, 226, 175);
+pub const SHRINK: Color = Color::srgb_u8(137, 180, 250);
+pub const SPEED_UP: Color = Color::srgb_u8(250, 179, 135);
+
+/// Timestep is divided by this every time a [`FoodKind::SpeedUp`] is eaten.
+const SPEED_UP_FACTOR: f64 = 1.15;
+
+/// What a piece of food does to the snake that eats it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FoodKind {
+    /// Always on the board, grows the snake by one
+    Normal,
+    /// Worth more and grows the snake by two, but only stays for a while
+    Bonus,
+    /// Takes two segments off the tail
+    Shrink,
+    /// Grows the snake by one and makes it move faster
+    SpeedUp,
+}
+
+impl FoodKind {
+    /// Kinds that occasionally appear next to the normal food
+    pub const SPECIAL: [FoodKind; 3] = [FoodKind::Bonus, FoodKind::Shrink, FoodKind::SpeedUp];
+
+    pub fn color(self) -> Color {
+        match self {
+            FoodKind::Normal => NORMAL,
+            FoodKind::Bonus => BONUS,
+            FoodKind::Shrink => SHRINK,
+            FoodKind::SpeedUp => SPEED_UP,
+        }
+    }
+
+    /// Points scored for eating it
+    pub fn points(self) -> u32 {
+        match self {
+            FoodKind::Normal => 1,
+            FoodKind::Bonus => 5,
+            FoodKind::Shrink => 2,
+            FoodKind::SpeedUp => 3,
+        }
+    }
+
+    /// Segments added to the snake, negative to remove them
+    pub fn growth(self) -> i32 {
+        match self {
+            FoodKind::Normal | FoodKind::SpeedUp => 1,
+            FoodKind::Bonus => 2,
+            FoodKind::Shrink => -2,
+        }
+    }
+
+    /// Seconds before it disappears uneaten, `None` to stay until eaten
+    pub fn lifetime(self) -> Option<f32> {
+        match self {
+            FoodKind::Bonus => Some(5.0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Food {
+    pub kind: FoodKind,
+    /// Counts down [`FoodKind::lifetime`]
+    pub expires: Option<Timer>,
+}
+
+impl Food {
+    pub fn new(kind: FoodKind) -> Self {
+        Self {
+            kind,
+            expires: kind
+                .lifetime()
+                .map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+        }
+    }
+}
+
+/// Cells not taken by the snake or other food.
+fn free_cells(grid: &Grid, taken: &[Position]) -> Vec<Position> {
+    (0..grid.width)
+        .flat_map(|x| (0..grid.height).map(move |y| Position { x, y }))
+        .filter(|cell| !taken.contains(cell))
+        .collect()
+}
+
+/// Keeps a normal piece of food on the board, now and then joined by a
+/// special one, always on free cells. When there is no free cell left for
+/// the normal food the snake has filled the board and won.
+pub(crate) fn spawn_food(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    config: Res<SnakeConfig>,
+    food: Query<(&Food, &Position)>,
+    segments: Query<&Position, With<SnakeSegment>>,
+    mut rng: ResMut<SnakeRng>,
+    mut next_state: ResMut<NextState<SnakeState>>,
+) {
+    if food.iter().any(|(food, _)| food.kind == FoodKind::Normal) {
+        return;
+    }
+
+    let mut taken: Vec<Position> = segments
+        .iter()
+        .chain(food.iter().map(|(_, position)| position))
+        .copied()
+        .collect();
+    let Some(&cell) = free_cells(&grid, &taken).choose(&mut rng.0) else {
+        next_state.set(SnakeState::Won);
+        return;
+    };
+    spawn(&mut commands, FoodKind::Normal, cell);
+    taken.push(cell);
+
+    let has_special = food.iter().any(|(food, _)| food.kind != FoodKind::Normal);
+    if !has_special && rng.0.random_bool(config.special_food_chance) {
+        let kind = *FoodKind::SPECIAL.choose(&mut rng.0).unwrap();
+        if let Some(&cell) = free_cells(&grid, &taken).choose(&mut rng.0) {
+            spawn(&mut commands, kind, cell);
+        }
+    }
+}
+
+fn spawn(commands: &mut Commands, kind: FoodKind, position: Position) {
+    commands.spawn((
+        Food::new(kind),
+        Sprite::from_color(kind.color(), Vec2 { x: 0.8, y: 0.8 }),
+        Size::square(1.0),
+        position,
+    ));
+}
+
+pub(crate) fn expire_food(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut food: Query<(Entity, &mut Food)>,
+) {
+    for (entity, mut food) in &mut food {
+        if let Some(timer) = &mut food.expires
+            && timer.tick(time.delta()).finished()
+        {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Heads of the snakes still alive.
+type LiveHead = (With<SnakeHead>, Without<Dead>);
+
+pub(crate) fn eat_food(
+    mut commands: Commands,
+    mut growth_writer: EventWriter<GrowthEvent>,
+    food_positions: Query<(Entity, &Food, &Position)>,
+    head_positions: Query<(Entity, &Position), LiveHead>,
+) {
+    for (snake, head_pos) in head_positions.iter() {
+        for (entity, food, food_pos) in food_positions.iter() {
+            if food_pos == head_pos {
+                commands.entity(entity).despawn();
+                growth_writer.write(GrowthEvent {
+                    snake,
+                    kind: food.kind,
+                });
+            }
+        }
+    }
+}
+
+/// Speeds the snake up for every [`FoodKind::SpeedUp`] eaten.
+pub(crate) fn apply_speed_ups(
+    mut growth_reader: EventReader<GrowthEvent>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    for GrowthEvent { kind, .. } in growth_reader.read() {
+        if *kind == FoodKind::SpeedUp {
+            let timestep = fixed_time.timestep().as_secs_f64() / SPEED_UP_FACTOR;
+            fixed_time.set_timestep_seconds(timestep);
+        }
+    }
+}
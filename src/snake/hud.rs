@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+
+use super::{
+    SnakeConfig, SnakeState, SnakeWinner, TEXT,
+    components::{SnakeHead, SnakeScore, SnakeSegments},
+    player_color,
+    score::{HighScores, RunClock, RunFinished},
+};
+
+const OVERLAY: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+/// Table rows shown on the results screen.
+const RESULTS_ROWS: usize = 5;
+
+const RESTART_PROMPT: &str = "Press Space or Start to play again";
+
+/// Text showing the score, length and time of the current run.
+#[derive(Component)]
+pub struct Hud;
+
+/// Summary of a finished run, shown until the next one starts.
+#[derive(Component)]
+pub struct ResultsScreen;
+
+/// Part of the [`Hud`] showing one player's score in versus.
+#[derive(Component)]
+pub(crate) struct PlayerSpan(u8);
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub(crate) fn spawn_hud(mut commands: Commands, config: Res<SnakeConfig>) {
+    let mut hud = commands.spawn((
+        Hud,
+        Text::default(),
+        TextColor(TEXT),
+        TextFont {
+            font_size: 30.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(16.0),
+            ..default()
+        },
+        GlobalZIndex(1),
+    ));
+    // A span per player in versus, coloured like their snake
+    if config.players > 1 {
+        hud.with_children(|hud| {
+            for player in 1..=config.players {
+                hud.spawn((
+                    PlayerSpan(player),
+                    TextSpan::default(),
+                    TextColor(player_color(player)),
+                    TextFont {
+                        font_size: 30.0,
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// Snakes ordered by player.
+fn by_player<'a>(
+    snakes: impl Iterator<Item = (&'a SnakeHead, &'a SnakeScore, &'a SnakeSegments)>,
+) -> Vec<(&'a SnakeHead, &'a SnakeScore, &'a SnakeSegments)> {
+    let mut snakes: Vec<_> = snakes.collect();
+    snakes.sort_by_key(|(head, _, _)| head.player);
+    snakes
+}
+
+pub(crate) fn update_hud(
+    config: Res<SnakeConfig>,
+    clock: Res<RunClock>,
+    snakes: Query<(&SnakeHead, &SnakeScore, &SnakeSegments)>,
+    mut hud: Query<&mut Text, With<Hud>>,
+    mut spans: Query<(&PlayerSpan, &mut TextSpan)>,
+) {
+    for mut text in &mut hud {
+        text.0 = if config.players <= 1 {
+            match snakes.iter().next() {
+                Some((_, score, segments)) => format!(
+                    "Score {}   Length {}   {}",
+                    score.points,
+                    segments.0.len(),
+                    format_time(clock.seconds)
+                ),
+                None => String::new(),
+            }
+        } else {
+            format_time(clock.seconds)
+        };
+    }
+    for (head, score, segments) in &snakes {
+        for (PlayerSpan(player), mut span) in &mut spans {
+            if *player == head.player {
+                span.0 = format!("   P{player} {} ({})", score.points, segments.0.len());
+            }
+        }
+    }
+}
+
+fn spawn_results(commands: &mut Commands, state: SnakeState, title: String, lines: [String; 2]) {
+    let [summary, table] = lines;
+    commands
+        .spawn((
+            Name::new("Results"),
+            ResultsScreen,
+            StateScoped(state),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(OVERLAY),
+            GlobalZIndex(2),
+        ))
+        .with_children(|screen| {
+            for (text, font_size) in [
+                (title, 60.0),
+                (summary, 30.0),
+                (table, 24.0),
+                (RESTART_PROMPT.to_string(), 24.0),
+            ] {
+                screen.spawn((
+                    Text::new(text),
+                    TextColor(TEXT),
+                    TextLayout::new_with_justify(JustifyText::Center),
+                    TextFont {
+                        font_size,
+                        ..default()
+                    },
+                ));
+            }
+        });
+}
+
+/// Shows how a single player run went and where it placed among the high
+/// scores.
+pub(crate) fn spawn_results_screen(
+    mut commands: Commands,
+    mut finished: EventReader<RunFinished>,
+    scores: Res<HighScores>,
+    screens: Query<Entity, With<ResultsScreen>>,
+) {
+    let Some(RunFinished { run, won, .. }) = finished.read().last() else {
+        return;
+    };
+    for entity in &screens {
+        commands.entity(entity).despawn();
+    }
+
+    let title = if *won { "You win!" } else { "Game over" };
+    let summary = format!(
+        "Length {}   Time {}   Score {}",
+        run.length,
+        format_time(run.seconds),
+        run.points
+    );
+    let mut table = String::from("High scores");
+    let placed = scores.entries.iter().position(|entry| entry == run);
+    for (rank, entry) in scores.entries.iter().take(RESULTS_ROWS).enumerate() {
+        let marker = if Some(rank) == placed { "> " } else { "" };
+        table.push_str(&format!(
+            "\n{marker}{}. {} points, length {}, {}",
+            rank + 1,
+            entry.points,
+            entry.length,
+            format_time(entry.seconds)
+        ));
+    }
+
+    let state = if *won {
+        SnakeState::Won
+    } else {
+        SnakeState::GameOver
+    };
+    spawn_results(&mut commands, state, title.to_string(), [summary, table]);
+}
+
+/// Shows who won a versus game and how every snake did.
+pub(crate) fn spawn_versus_results(
+    mut commands: Commands,
+    state: Res<State<SnakeState>>,
+    winner: Option<Res<SnakeWinner>>,
+    clock: Res<RunClock>,
+    snakes: Query<(&SnakeHead, &SnakeScore, &SnakeSegments)>,
+) {
+    let title = match winner.and_then(|winner| winner.0) {
+        Some(player) => format!("Player {player} wins!"),
+        None => "Draw".to_string(),
+    };
+    let summary = format!("Time {}", format_time(clock.seconds));
+    let table = by_player(snakes.iter())
+        .into_iter()
+        .map(|(head, score, segments)| {
+            format!(
+                "Player {}: {} points, length {}",
+                head.player,
+                score.points,
+                segments.0.len()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    spawn_results(&mut commands, *state.get(), title, [summary, table]);
+}
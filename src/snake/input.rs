@@ -0,0 +1,178 @@
+use bevy::{
+    input::{ButtonState, keyboard::KeyboardInput},
+    math::CompassQuadrant,
+    platform::collections::HashMap,
+    prelude::*,
+};
+
+use super::{
+    RestartGame, SnakeConfig,
+    components::{Direction, InputQueue, SnakeHead},
+};
+use crate::stick::StickSettings;
+
+/// Keys steering each snake, keyed by [`SnakeHead::player`].
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SnakeKeys {
+    pub players: HashMap<u8, Vec<(KeyCode, Direction)>>,
+}
+
+fn keys(up: KeyCode, down: KeyCode, left: KeyCode, right: KeyCode) -> Vec<(KeyCode, Direction)> {
+    vec![
+        (up, Direction::Up),
+        (down, Direction::Down),
+        (left, Direction::Left),
+        (right, Direction::Right),
+    ]
+}
+
+impl SnakeKeys {
+    /// Default keys for `players` snakes: a lone player steers with the arrow
+    /// keys or WASD, otherwise players get WASD, the arrows, IJKL and the
+    /// number pad in turn.
+    pub fn for_players(players: u8) -> Self {
+        let wasd = keys(KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyA, KeyCode::KeyD);
+        let arrows = keys(
+            KeyCode::ArrowUp,
+            KeyCode::ArrowDown,
+            KeyCode::ArrowLeft,
+            KeyCode::ArrowRight,
+        );
+        let mut players_keys = HashMap::default();
+        if players <= 1 {
+            players_keys.insert(1, [arrows, wasd].concat());
+        } else {
+            let layouts = [
+                wasd,
+                arrows,
+                keys(KeyCode::KeyI, KeyCode::KeyK, KeyCode::KeyJ, KeyCode::KeyL),
+                keys(
+                    KeyCode::Numpad8,
+                    KeyCode::Numpad5,
+                    KeyCode::Numpad4,
+                    KeyCode::Numpad6,
+                ),
+            ];
+            for (player, layout) in (1..=players).zip(layouts) {
+                players_keys.insert(player, layout);
+            }
+        }
+        Self {
+            players: players_keys,
+        }
+    }
+
+    fn lookup(&self, key: KeyCode) -> impl Iterator<Item = (u8, Direction)> + '_ {
+        self.players.iter().flat_map(move |(&player, keys)| {
+            keys.iter()
+                .filter(move |(bound, _)| *bound == key)
+                .map(move |(_, direction)| (player, *direction))
+        })
+    }
+}
+
+/// Player slot a connected gamepad steers.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GamepadSlot(pub u8);
+
+fn push_turn(heads: &mut Query<(&SnakeHead, &mut InputQueue)>, player: u8, direction: Direction) {
+    for (head, mut queue) in heads {
+        if head.player == player {
+            queue.push(direction);
+        }
+    }
+}
+
+/// Queues key presses in the order they happened, even when several arrive
+/// in the same frame.
+pub(crate) fn handle_keyboard_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    keys: Res<SnakeKeys>,
+    mut heads: Query<(&SnakeHead, &mut InputQueue)>,
+) {
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed || event.repeat {
+            continue;
+        }
+        for (player, direction) in keys.lookup(event.key_code) {
+            push_turn(&mut heads, player, direction);
+        }
+    }
+}
+
+/// Hands newly connected gamepads the first free player slot, and frees the
+/// slot of gamepads that disconnect.
+pub(crate) fn assign_gamepads(
+    mut commands: Commands,
+    config: Res<SnakeConfig>,
+    unassigned: Query<Entity, (With<Gamepad>, Without<GamepadSlot>)>,
+    assigned: Query<&GamepadSlot, With<Gamepad>>,
+    mut disconnected: RemovedComponents<Gamepad>,
+) {
+    for entity in disconnected.read() {
+        if let Ok(mut entity) = commands.get_entity(entity) {
+            entity.remove::<GamepadSlot>();
+        }
+    }
+
+    let mut taken: Vec<u8> = assigned.iter().map(|slot| slot.0).collect();
+    for entity in &unassigned {
+        let Some(player) = (1..=config.players.max(1)).find(|player| !taken.contains(player))
+        else {
+            break;
+        };
+        info!("Gamepad {entity} steers snake {player}");
+        commands.entity(entity).insert(GamepadSlot(player));
+        taken.push(player);
+    }
+}
+
+pub(crate) fn handle_gamepad_input(
+    gamepads: Query<(Entity, &Gamepad, &GamepadSlot)>,
+    stick_settings: Res<StickSettings>,
+    // Last direction each gamepad's left stick was quantized to
+    mut stick_directions: Local<HashMap<Entity, CompassQuadrant>>,
+    mut heads: Query<(&SnakeHead, &mut InputQueue)>,
+) {
+    for (entity, gamepad, slot) in &gamepads {
+        let previous = stick_directions.get(&entity).copied();
+        let quadrant = stick_settings.quantize(gamepad.left_stick(), previous);
+        match quadrant {
+            Some(quadrant) => stick_directions.insert(entity, quadrant),
+            None => stick_directions.remove(&entity),
+        };
+        // Only turn when the stick moves to a new direction, so holding it
+        // doesn't override the D-pad and keyboard
+        let stick = quadrant
+            .filter(|quadrant| Some(*quadrant) != previous)
+            .map(Direction::from_quadrant);
+
+        let dpad = [
+            (GamepadButton::DPadUp, Direction::Up),
+            (GamepadButton::DPadDown, Direction::Down),
+            (GamepadButton::DPadLeft, Direction::Left),
+            (GamepadButton::DPadRight, Direction::Right),
+        ]
+        .into_iter()
+        .filter(|(button, _)| gamepad.just_pressed(*button))
+        .map(|(_, direction)| direction);
+
+        for direction in stick.into_iter().chain(dpad) {
+            push_turn(&mut heads, slot.0, direction);
+        }
+    }
+}
+
+/// Starts a new run from the game over and win screens.
+pub(crate) fn handle_restart_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut restart: EventWriter<RestartGame>,
+) {
+    let gamepad_pressed = gamepads
+        .iter()
+        .any(|gamepad| gamepad.any_just_pressed([GamepadButton::South, GamepadButton::Start]));
+    if keyboard_input.any_just_pressed([KeyCode::Space, KeyCode::Enter]) || gamepad_pressed {
+        restart.write(RestartGame);
+    }
+}
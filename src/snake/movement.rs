@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+
+use super::{
+    GrowthEvent, SnakeDied,
+    board::Grid,
+    components::{AiSnake, Dead, InputQueue, LastTailPosition, Position, SnakeHead, SnakeSegments},
+    spawn_segment,
+};
+
+/// Heads of the snakes still alive that players steer.
+type LivePlayerHead = (Without<Dead>, Without<AiSnake>);
+
+/// Applies the next queued turn, checked against the direction the snake
+/// actually moved in rather than the last key pressed.
+pub(crate) fn steer_snake(mut heads: Query<(&mut SnakeHead, &mut InputQueue), LivePlayerHead>) {
+    for (mut head, mut queue) in &mut heads {
+        if let Some(direction) = queue.next_turn(head.direction) {
+            head.direction = direction;
+        }
+    }
+}
+
+/// Moves every head one cell and every segment into the cell in front of it,
+/// then looks for crashes once all snakes have moved.
+///
+/// A snake dies when its head leaves the board through a solid edge, or lands
+/// on a cell covered by any body after moving, so following a tail is safe.
+/// Heads meeting on the same cell, or passing through each other, kill both.
+pub(crate) fn move_snake(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    mut snakes: Query<(
+        Entity,
+        &SnakeHead,
+        &SnakeSegments,
+        &mut LastTailPosition,
+        Has<Dead>,
+    )>,
+    mut positions: Query<&mut Position>,
+    mut died: EventWriter<SnakeDied>,
+) {
+    let mut moved = Vec::new();
+    let mut crashed = Vec::new();
+    for (entity, head, segments, mut last_tail_position, dead) in &mut snakes {
+        if dead {
+            continue;
+        }
+        let segment_positions = segments
+            .0
+            .iter()
+            .map(|e| *positions.get(*e).unwrap())
+            .collect::<Vec<Position>>();
+        let Some(next) = grid.step(segment_positions[0], head.direction) else {
+            crashed.push(entity);
+            continue;
+        };
+
+        segment_positions
+            .iter()
+            .zip(segments.0.iter().skip(1))
+            .for_each(|(pos, segment)| {
+                *positions.get_mut(*segment).unwrap() = *pos;
+            });
+        *positions.get_mut(segments.0[0]).unwrap() = next;
+
+        *last_tail_position = LastTailPosition(Some(*segment_positions.last().unwrap()));
+        moved.push((entity, segment_positions[0], next));
+    }
+
+    // Every covered cell after moving, with the snake covering it and
+    // whether it is that snake's head
+    let mut covered = Vec::new();
+    for (entity, _, segments, _, _) in &snakes {
+        for (i, segment) in segments.0.iter().enumerate() {
+            covered.push((entity, i == 0, *positions.get(*segment).unwrap()));
+        }
+    }
+    for &(entity, from, to) in &moved {
+        let hit = covered
+            .iter()
+            .any(|&(owner, is_head, cell)| cell == to && !(owner == entity && is_head));
+        let swapped = moved.iter().any(|&(other, other_from, other_to)| {
+            other != entity && other_from == to && other_to == from
+        });
+        if hit || swapped {
+            crashed.push(entity);
+        }
+    }
+
+    for entity in crashed {
+        commands.entity(entity).insert(Dead);
+        died.write(SnakeDied(entity));
+    }
+}
+
+/// Adds segments at the tail for food that grows the snake, and removes
+/// them for food that shrinks it, never taking the head.
+pub(crate) fn grow_snake(
+    mut commands: Commands,
+    mut snakes: Query<(&SnakeHead, &mut SnakeSegments, &LastTailPosition)>,
+    mut growth_reader: EventReader<GrowthEvent>,
+) {
+    for GrowthEvent { snake, kind } in growth_reader.read() {
+        let Ok((head, mut segments, last_tail_position)) = snakes.get_mut(*snake) else {
+            continue;
+        };
+        let growth = kind.growth();
+        if growth > 0 {
+            for _ in 0..growth {
+                let segment =
+                    spawn_segment(&mut commands, head.player, last_tail_position.0.unwrap());
+                segments.0.push(segment);
+            }
+        } else {
+            let keep = segments
+                .0
+                .len()
+                .saturating_sub(growth.unsigned_abs() as usize);
+            for entity in segments.0.drain(keep.max(1)..) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+
+use super::{
+    board::Grid,
+    components::{Position, Size},
+};
+
+/// Resolution the board is mapped to when there is no window, e.g. when
+/// running under `MinimalPlugins`.
+pub const HEADLESS_RESOLUTION: Vec2 = Vec2::new(1000.0, 1000.0);
+
+fn resolution(window: Option<Single<&Window>>) -> Vec2 {
+    window.map_or(HEADLESS_RESOLUTION, |window| window.size())
+}
+
+pub(crate) fn size_scaling(
+    window: Option<Single<&Window>>,
+    grid: Res<Grid>,
+    mut query: Query<(&Size, &mut Transform)>,
+) {
+    let resolution = resolution(window);
+    for (size, mut transform) in query.iter_mut() {
+        transform.scale = Vec3::new(
+            size.width / grid.width as f32 * resolution.x,
+            size.height / grid.height as f32 * resolution.y,
+            1.0,
+        );
+    }
+}
+
+pub(crate) fn position_translation(
+    window: Option<Single<&Window>>,
+    grid: Res<Grid>,
+    mut query: Query<(&Position, &mut Transform)>,
+) {
+    fn convert(position: f32, bound_window: f32, bound_game: f32) -> f32 {
+        let tile_size = bound_window / bound_game;
+        position / bound_game * bound_window - (bound_window / 2.) + (tile_size / 2.)
+    }
+
+    let resolution = resolution(window);
+    for (position, mut transform) in query.iter_mut() {
+        transform.translation = Vec3::new(
+            convert(position.x as f32, resolution.x, grid.width as f32),
+            convert(position.y as f32, resolution.y, grid.height as f32),
+            transform.translation.z,
+        );
+    }
+}
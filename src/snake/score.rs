@@ -0,0 +1,188 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    GrowthEvent, SnakeConfig,
+    components::{SnakeScore, SnakeSegments},
+};
+
+/// Time spent in the current run.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub struct RunClock {
+    /// Seconds since the snakes spawned
+    pub seconds: f32,
+}
+
+/// A finished run, as kept in the [`HighScores`] table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct HighScore {
+    pub points: u32,
+    /// Segments, head included, when the run ended
+    pub length: u32,
+    pub seconds: f32,
+}
+
+/// Sent when a single player run ends, by dying or by filling the board.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RunFinished {
+    pub run: HighScore,
+    pub won: bool,
+    /// The computer steered the snake, which keeps the run out of the high
+    /// scores
+    pub ai: bool,
+}
+
+/// Best runs so far, highest points first, saved to
+/// [`SnakeConfig::high_score_file`] after every run.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Runs kept in the table
+    pub const MAX_ENTRIES: usize = 10;
+
+    /// Adds a run, returning its place in the table or `None` if it did not
+    /// make the cut. Ties go to the longer snake, then the faster run.
+    pub fn insert(&mut self, run: HighScore) -> Option<usize> {
+        let rank = self.entries.partition_point(|entry| {
+            (entry.points, entry.length) > (run.points, run.length)
+                || ((entry.points, entry.length) == (run.points, run.length)
+                    && entry.seconds <= run.seconds)
+        });
+        if rank >= Self::MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(rank, run);
+        self.entries.truncate(Self::MAX_ENTRIES);
+        Some(rank)
+    }
+
+    /// Reads the table at `path`. A missing file gives an empty table, and so
+    /// does an unreadable one, which is moved aside to `<path>.corrupt` so the
+    /// next save doesn't destroy it.
+    pub fn load(path: &Path) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(error) => {
+                warn!(
+                    "Could not read high scores from {}: {error}",
+                    path.display()
+                );
+                return Self::default();
+            }
+        };
+        match ron::from_str::<Self>(&text) {
+            Ok(mut scores) => {
+                scores.entries.truncate(Self::MAX_ENTRIES);
+                scores
+            }
+            Err(error) => {
+                let backup = corrupt_path(path);
+                warn!(
+                    "High scores in {} are corrupted ({error}), moving them to {}",
+                    path.display(),
+                    backup.display()
+                );
+                if let Err(error) = fs::rename(path, &backup) {
+                    warn!("Could not move corrupted high scores aside: {error}");
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the table to `path`, creating its directory if needed. The file
+    /// is replaced in one step so a crash never leaves it half written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, text)?;
+        fs::rename(&temporary, path)
+    }
+}
+
+fn corrupt_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".corrupt");
+    backup.into()
+}
+
+/// Where the high score table lives by default: in [`data_dir`], or `None`
+/// if that can't be found.
+///
+/// [`data_dir`]: crate::data_dir
+pub fn default_high_score_file() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("snake_high_scores.ron"))
+}
+
+pub(crate) fn load_high_scores(mut commands: Commands, config: Res<SnakeConfig>) {
+    let scores = config
+        .high_score_file
+        .as_deref()
+        .map(HighScores::load)
+        .unwrap_or_default();
+    commands.insert_resource(scores);
+}
+
+pub(crate) fn tick_run_clock(time: Res<Time>, mut clock: ResMut<RunClock>) {
+    clock.seconds += time.delta_secs();
+}
+
+pub(crate) fn add_points(
+    mut growth_reader: EventReader<GrowthEvent>,
+    mut scores: Query<&mut SnakeScore>,
+) {
+    for GrowthEvent { snake, kind } in growth_reader.read() {
+        if let Ok(mut score) = scores.get_mut(*snake) {
+            score.points += kind.points();
+        }
+    }
+}
+
+/// Snapshot of a snake's run for the high score table.
+pub(crate) fn current_run(
+    score: &SnakeScore,
+    segments: &SnakeSegments,
+    clock: &RunClock,
+) -> HighScore {
+    HighScore {
+        points: score.points,
+        length: segments.0.len() as u32,
+        seconds: clock.seconds,
+    }
+}
+
+/// Enters runs players finished themselves into the table and saves it.
+pub(crate) fn record_high_scores(
+    mut finished: EventReader<RunFinished>,
+    mut scores: ResMut<HighScores>,
+    config: Res<SnakeConfig>,
+) {
+    if config.attract {
+        finished.clear();
+        return;
+    }
+    for RunFinished { run, ai, .. } in finished.read() {
+        if *ai || scores.insert(*run).is_none() {
+            continue;
+        }
+        if let Some(path) = &config.high_score_file
+            && let Err(error) = scores.save(path)
+        {
+            warn!("Could not save high scores to {}: {error}", path.display());
+        }
+    }
+}
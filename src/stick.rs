@@ -0,0 +1,89 @@
+//! Shared handling of analog sticks: deadzones, response curves and
+//! quantizing a stick into one of four directions.
+
+use bevy::{math::CompassQuadrant, prelude::*};
+
+/// How stick deflection past the deadzone maps to output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseCurve {
+    Linear,
+    /// Raises the deflection to this power, values above 1.0 give finer
+    /// control near the centre
+    Power(f32),
+}
+
+impl ResponseCurve {
+    fn apply(self, value: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Power(exponent) => value.powf(exponent),
+        }
+    }
+}
+
+/// How raw stick input is turned into movement.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct StickSettings {
+    /// Deflection below which the stick reads as centred
+    pub deadzone: f32,
+    /// Deflection above which the stick reads as fully pushed
+    pub outer_deadzone: f32,
+    pub curve: ResponseCurve,
+    /// Angle in radians the stick has to move past the boundary between two
+    /// directions before [`StickSettings::quantize`] switches between them
+    pub hysteresis: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.2,
+            outer_deadzone: 0.95,
+            curve: ResponseCurve::Power(1.5),
+            hysteresis: 10f32.to_radians(),
+        }
+    }
+}
+
+impl StickSettings {
+    /// Rescales a deflection in `0.0..=1.0` to start at the deadzone and
+    /// saturate at the outer deadzone, then applies the response curve.
+    fn scale(&self, magnitude: f32) -> f32 {
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+        let range = (self.outer_deadzone - self.deadzone).max(f32::EPSILON);
+        self.curve
+            .apply(((magnitude - self.deadzone) / range).clamp(0.0, 1.0))
+    }
+
+    /// Processed value of a single axis, in `-1.0..=1.0`
+    pub fn axis(&self, raw: f32) -> f32 {
+        self.scale(raw.abs()) * raw.signum()
+    }
+
+    /// Processed stick, using a radial deadzone so diagonals behave the same
+    /// as the axes
+    pub fn stick(&self, raw: Vec2) -> Vec2 {
+        raw.normalize_or_zero() * self.scale(raw.length())
+    }
+
+    /// Direction the stick is pushed in, or `None` when it is centred.
+    ///
+    /// While the stick stays within [`Self::hysteresis`] of the sector of
+    /// `current`, `current` is kept, so wobbling around a diagonal does not
+    /// flip between directions.
+    pub fn quantize(&self, raw: Vec2, current: Option<CompassQuadrant>) -> Option<CompassQuadrant> {
+        if self.scale(raw.length()) == 0.0 {
+            return None;
+        }
+        let direction = Dir2::new(raw).ok()?;
+        if let Some(current) = current {
+            let angle = Dir2::from(current).angle_to(*direction).abs();
+            if angle <= std::f32::consts::FRAC_PI_4 + self.hysteresis {
+                return Some(current);
+            }
+        }
+        Some(CompassQuadrant::from(direction))
+    }
+}
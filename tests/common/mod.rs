@@ -0,0 +1,80 @@
+//! Fixtures shared by the test suites, the pong ones here and the snake ones
+//! in [`snake`].
+
+// Every suite builds this module on its own and uses only some of it
+#![allow(dead_code)]
+
+pub mod snake;
+
+use std::time::Duration;
+
+use bevy::{
+    app::Plugins, input::InputPlugin, prelude::*, state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+};
+use first_bevy_game::pong::*;
+
+/// How far time moves on every update of a [`headless_app`].
+pub const TICK: Duration = Duration::from_millis(16);
+
+/// An app without a window running `plugins`, finished the way `App::run`
+/// would, since replicon only sets up its events then.
+pub fn app_with<M>(plugins: impl Plugins<M>) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, InputPlugin))
+        .add_plugins(plugins);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// A pong match stepping [`TICK`] every update, past its first frame.
+pub fn headless_app(config: PongConfig) -> App {
+    let mut app = app_with(PongPlugin { config });
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(TICK));
+    app.update();
+    app
+}
+
+/// Updates `app` until at least `duration` of its manually stepped time has
+/// passed.
+pub fn run_for(app: &mut App, duration: Duration) {
+    let &TimeUpdateStrategy::ManualDuration(frame) = app.world().resource::<TimeUpdateStrategy>()
+    else {
+        panic!("run_for needs an app stepping a fixed duration");
+    };
+    for _ in 0..duration.div_duration_f64(frame).ceil() as u32 {
+        app.update();
+    }
+}
+
+pub fn ball(app: &mut App) -> (Position, Velocity) {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<(&Position, &Velocity), With<Ball>>();
+    let (position, velocity) = query.single(app.world()).unwrap();
+    (*position, *velocity)
+}
+
+pub fn set_ball(app: &mut App, position: Vec2, velocity: Vec2) {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<(&mut Position, &mut Velocity), With<Ball>>();
+    let (mut ball_position, mut ball_velocity) = query.single_mut(app.world_mut()).unwrap();
+    ball_position.0 = position;
+    ball_velocity.0 = velocity;
+}
+
+pub fn paddle_entity(app: &mut App, player: u8) -> Entity {
+    let mut query = app.world_mut().query::<(Entity, &Paddle)>();
+    query
+        .iter(app.world())
+        .find(|(_, paddle)| paddle.player == player)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+pub fn paddle(app: &mut App, player: u8) -> Position {
+    let entity = paddle_entity(app, player);
+    *app.world().get::<Position>(entity).unwrap()
+}
@@ -0,0 +1,76 @@
+//! Fixtures shared by the snake test suites.
+
+use std::time::Duration;
+
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput, NativeKey},
+    },
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use first_bevy_game::snake::*;
+
+use super::app_with;
+
+/// A game of snake where every update runs exactly one tick, past its first
+/// frame.
+pub fn snake_app(config: SnakeConfig) -> App {
+    let tick = Duration::from_secs_f64(1.0 / config.grid.tick_rate);
+    let mut app = app_with(SnakePlugin { config });
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+    app.update();
+    app
+}
+
+/// A [`snake_app`] with its only snake heading right.
+pub fn solo_app(config: SnakeConfig) -> App {
+    let mut app = snake_app(config);
+    let mut head = app.world_mut().query::<&mut SnakeHead>();
+    head.single_mut(app.world_mut()).unwrap().direction = Direction::Right;
+    app
+}
+
+pub fn state(app: &App) -> SnakeState {
+    *app.world().resource::<State<SnakeState>>().get()
+}
+
+/// Cells of the only snake, head first.
+pub fn snake_cells(app: &mut App) -> Vec<Position> {
+    let mut segments = app.world_mut().query::<&SnakeSegments>();
+    let segments = segments.single(app.world()).unwrap().0.clone();
+    segments
+        .iter()
+        .map(|entity| *app.world().get::<Position>(*entity).unwrap())
+        .collect()
+}
+
+/// Replaces all food with `kind` right in front of a snake heading right,
+/// then runs a tick so it gets eaten.
+pub fn feed(app: &mut App, kind: FoodKind) {
+    let mut query = app.world_mut().query_filtered::<Entity, With<Food>>();
+    for entity in query.iter(app.world()).collect::<Vec<_>>() {
+        app.world_mut().despawn(entity);
+    }
+    let grid = *app.world().resource::<Grid>();
+    let ahead = grid.step(snake_cells(app)[0], Direction::Right).unwrap();
+    app.world_mut().spawn((Food::new(kind), ahead));
+    app.update();
+}
+
+/// Presses and releases each of `keys` in turn.
+pub fn press(app: &mut App, keys: &[KeyCode]) {
+    for &key_code in keys {
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            app.world_mut().send_event(KeyboardInput {
+                key_code,
+                logical_key: Key::Unidentified(NativeKey::Unidentified),
+                state,
+                text: None,
+                repeat: false,
+                window: Entity::PLACEHOLDER,
+            });
+        }
+    }
+}
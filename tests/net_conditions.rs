@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use bevy_replicon::prelude::Channel;
+use first_bevy_game::net::*;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+/// Pushes messages numbered 0 to `count` one millisecond apart, returning
+/// their numbers in the order they arrive by `until`.
+fn send(kind: Channel, conditions: &str, count: u8, until: Duration) -> Vec<u8> {
+    let conditions: LinkConditions = conditions.parse().unwrap();
+    let mut line = DelayLine::new(Some(7));
+    for number in 0..count {
+        line.push(ms(number.into()), (), 0, kind, vec![number], &conditions);
+    }
+    line.pop_due(until)
+        .into_iter()
+        .map(|((), _, message)| message[0])
+        .collect()
+}
+
+#[test]
+fn conditions_parse_from_the_command_line_form() {
+    let conditions: LinkConditions = "latency=80, jitter=20,loss=5,duplication=1,reordering=2.5"
+        .parse()
+        .unwrap();
+    assert_eq!(conditions.latency, ms(80));
+    assert_eq!(conditions.jitter, ms(20));
+    assert_eq!(conditions.loss, 0.05);
+    assert_eq!(conditions.duplication, 0.01);
+    assert_eq!(conditions.reordering, 0.025);
+    assert!("none".parse::<LinkConditions>().unwrap().is_perfect());
+    assert!("loss=150".parse::<LinkConditions>().is_err());
+    assert!("lag=80".parse::<LinkConditions>().is_err());
+    assert!("latency=-5".parse::<LinkConditions>().is_err());
+
+    assert_eq!("to-server/3".parse(), Ok(ChannelId::ToServer(3)));
+    assert_eq!("to-clients/0".parse(), Ok(ChannelId::ToClients(0)));
+    assert!("sideways/1".parse::<ChannelId>().is_err());
+}
+
+#[test]
+fn latency_holds_messages_back() {
+    let mut line = DelayLine::new(Some(7));
+    let conditions = "latency=50".parse().unwrap();
+    line.push(ms(0), (), 2, Channel::Ordered, vec![1], &conditions);
+    assert!(line.pop_due(ms(49)).is_empty());
+    assert_eq!(line.pop_due(ms(50)), [((), 2, vec![1])]);
+    assert!(line.is_empty());
+}
+
+#[test]
+fn only_unreliable_messages_are_lost_or_duplicated() {
+    assert!(send(Channel::Unreliable, "loss=100", 10, ms(1000)).is_empty());
+    assert_eq!(
+        send(Channel::Unreliable, "duplication=100", 3, ms(1000)).len(),
+        6
+    );
+    // Reliable channels resend lost messages instead, which takes longer
+    let resent = send(Channel::Unordered, "latency=10,loss=50", 20, ms(5000));
+    assert_eq!(resent.len(), 20);
+    assert!(send(Channel::Unordered, "latency=10,loss=50", 20, ms(30)).len() < 20);
+    assert_eq!(
+        send(Channel::Ordered, "duplication=100", 3, ms(1000)),
+        [0, 1, 2]
+    );
+}
+
+#[test]
+fn ordered_channels_keep_their_order() {
+    let conditions = "latency=50,jitter=40,loss=30,reordering=50";
+    let unordered = send(Channel::Unordered, conditions, 50, ms(5000));
+    assert!(unordered.windows(2).any(|pair| pair[0] > pair[1]));
+    let ordered = send(Channel::Ordered, conditions, 50, ms(5000));
+    assert_eq!(ordered, (0..50).collect::<Vec<_>>());
+}
+
+#[test]
+fn channels_can_have_their_own_conditions() {
+    let mut conditions = NetworkConditions {
+        all: "latency=80".parse().unwrap(),
+        ..Default::default()
+    };
+    conditions
+        .channels
+        .insert(ChannelId::ToServer(2), "loss=10".parse().unwrap());
+    assert_eq!(conditions.channel(ChannelId::ToClients(2)).latency, ms(80));
+    assert_eq!(conditions.channel(ChannelId::ToServer(2)).loss, 0.1);
+    assert_eq!(conditions.channel(ChannelId::ToServer(2)).latency, ms(0));
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use first_bevy_game::{net::*, pong::Position};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn snapshots(values: &[(u64, f32)]) -> Snapshots<Position> {
+    let mut snapshots = Snapshots::default();
+    for &(at, x) in values {
+        snapshots.push(ms(at), Position(Vec2::new(x, 50.0)));
+    }
+    snapshots
+}
+
+fn x(position: Option<Position>) -> f32 {
+    position.unwrap().0.x
+}
+
+#[test]
+fn blends_between_the_snapshots_either_side() {
+    let snapshots = snapshots(&[(0, 10.0), (100, 20.0), (200, 20.0)]);
+    assert_eq!(x(snapshots.sample(ms(50), ms(50))), 15.0);
+    assert_eq!(x(snapshots.sample(ms(150), ms(50))), 20.0);
+    // Before anything arrived it shows the oldest snapshot
+    assert_eq!(x(snapshots.sample(Duration::ZERO, ms(50))), 10.0);
+    assert_eq!(
+        Snapshots::<Position>::default().sample(ms(50), ms(50)),
+        None
+    );
+}
+
+#[test]
+fn late_packets_are_extrapolated_up_to_the_limit() {
+    let snapshots = snapshots(&[(0, 10.0), (100, 20.0)]);
+    assert_eq!(x(snapshots.sample(ms(140), ms(50))), 24.0);
+    // Past the limit it settles on the last value received
+    assert_eq!(x(snapshots.sample(ms(200), ms(50))), 20.0);
+}
+
+#[test]
+fn teleports_snap_instead_of_sliding() {
+    let snapshots = snapshots(&[(0, 90.0), (20, 50.0)]);
+    assert_eq!(x(snapshots.sample(ms(5), ms(50))), 50.0);
+    assert_eq!(x(snapshots.sample(ms(40), ms(50))), 50.0);
+}
+
+#[test]
+fn only_needed_snapshots_are_kept() {
+    let mut snapshots = snapshots(&[(0, 0.0), (10, 1.0), (20, 2.0), (30, 3.0)]);
+    // Two arriving in the same frame keep the newest
+    snapshots.push(ms(30), Position(Vec2::new(4.0, 50.0)));
+    assert_eq!(snapshots.len(), 4);
+
+    snapshots.prune(ms(25));
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(x(snapshots.sample(ms(25), ms(50))), 3.0);
+}
+
+#[test]
+fn replicated_positions_move_smoothly() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InterpolationPlugin::<Position>::default()))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(ms(10)));
+    let entity = app
+        .world_mut()
+        .spawn((Position::default(), Snapshots::<Position>::default()))
+        .id();
+
+    // The server moves it 5 units every 50ms, frames are 10ms apart
+    let mut shown = Vec::new();
+    for frame in 0..60 {
+        if frame % 5 == 0 {
+            app.world_mut().get_mut::<Position>(entity).unwrap().0.x = frame as f32;
+        }
+        app.update();
+        shown.push(app.world().get::<Position>(entity).unwrap().0.x);
+    }
+    // Once the delay has passed it moves a little every frame instead of
+    // jumping every fifth
+    for pair in shown[20..].windows(2) {
+        assert!((pair[1] - pair[0] - 1.0).abs() < 0.01, "{shown:?}");
+    }
+    assert!(shown[59] < 59.0 - 5.0);
+}
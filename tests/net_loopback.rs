@@ -0,0 +1,393 @@
+//! Runs a server and its clients in the same process over the loopback
+//! transport, with a fixed frame time so every run plays out the same.
+
+use std::time::Duration;
+
+use bevy::{diagnostic::DiagnosticsStore, prelude::*, time::TimeUpdateStrategy};
+use common::*;
+use first_bevy_game::{net::*, pong::*};
+
+mod common;
+
+fn app(role: NetRole, name: &str) -> App {
+    conditioned_app(role, name, default())
+}
+
+fn conditioned_app(role: NetRole, name: &str, conditions: NetworkConditions) -> App {
+    let mut app = app_with(NetPongPlugin {
+        role,
+        pong: PongConfig {
+            seed: Some(3),
+            start_delay: 0.5,
+            ..default()
+        },
+        player: PlayerInfo {
+            name: name.to_string(),
+            ..default()
+        },
+        conditions,
+        ..default()
+    });
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / 60.0,
+    )));
+    app
+}
+
+fn server(loopback: &Loopback) -> App {
+    app(
+        NetRole::Server(ServerSettings {
+            transport: Transport::Loopback(loopback.clone()),
+            ..default()
+        }),
+        "Host",
+    )
+}
+
+fn client(loopback: &Loopback, name: &str) -> App {
+    app(
+        NetRole::Client(ClientSettings {
+            transport: Transport::Loopback(loopback.clone()),
+            ..default()
+        }),
+        name,
+    )
+}
+
+fn step(apps: &mut [App], frames: usize) {
+    for _ in 0..frames {
+        for app in apps.iter_mut() {
+            app.update();
+        }
+    }
+}
+
+fn lobby(app: &mut App) -> Vec<LobbyPlayer> {
+    let mut query = app.world_mut().query::<&LobbyPlayer>();
+    let mut players: Vec<_> = query.iter(app.world()).cloned().collect();
+    players.sort_by_key(|player| (player.slot.is_none(), player.slot));
+    players
+}
+
+#[test]
+fn single_player_runs_the_match_through_the_server() {
+    let mut game = app(NetRole::SinglePlayer, "Solo");
+    step(std::slice::from_mut(&mut game), 2);
+    assert_eq!(
+        *game.world().resource::<State<NetState>>().get(),
+        NetState::InGame
+    );
+    assert_eq!(*game.world().resource::<LocalPaddle>(), LocalPaddle(1));
+    assert_eq!(lobby(&mut game)[0].info.name, "Solo");
+
+    let start = ball(&mut game).0;
+    step(std::slice::from_mut(&mut game), 60);
+    assert_ne!(ball(&mut game).0, start);
+}
+
+#[test]
+fn one_server_hosts_several_clients() {
+    let loopback = Loopback::default();
+    let mut apps = vec![server(&loopback)];
+    for name in ["Ada", "Bo", "Cy"] {
+        apps.push(client(&loopback, name));
+    }
+    step(&mut apps, 10);
+
+    for app in &mut apps {
+        let players = lobby(app);
+        let names: Vec<_> = players
+            .iter()
+            .map(|player| player.info.name.as_str())
+            .collect();
+        assert_eq!(names, ["Host", "Ada", "Bo", "Cy"]);
+        assert_eq!(players[1].slot, Some(2));
+        assert_eq!(players[2].slot, None);
+    }
+    assert_eq!(*apps[1].world().resource::<LocalPaddle>(), LocalPaddle(2));
+    assert!(!apps[2].world().contains_resource::<LocalPaddle>());
+}
+
+#[test]
+fn only_paddles_of_others_are_interpolated() {
+    let loopback = Loopback::default();
+    let mut apps = vec![server(&loopback), client(&loopback, "Guest")];
+    step(&mut apps, 10);
+    let local = paddle_entity(&mut apps[1], 2);
+    let other = paddle_entity(&mut apps[1], 1);
+    let client = apps[1].world();
+    assert!(client.get::<Prediction>(local).is_some());
+    assert!(client.get::<Snapshots<Position>>(local).is_none());
+    assert!(client.get::<Snapshots<Position>>(other).is_some());
+}
+
+/// Readies up a server and one client, then holds the client's up key for a
+/// second, returning where the ball was on the client every frame.
+fn play(loopback: &Loopback) -> Vec<Position> {
+    let mut apps = vec![server(loopback), client(loopback, "Guest")];
+    step(&mut apps, 10);
+    for app in &mut apps {
+        app.world_mut().send_event(SetReady(true));
+    }
+    apps[1]
+        .world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::ArrowUp);
+    (0..60)
+        .map(|_| {
+            step(&mut apps, 1);
+            ball(&mut apps[1]).0
+        })
+        .collect()
+}
+
+#[test]
+fn matches_play_out_the_same_every_run() {
+    let first = play(&Loopback::default());
+    assert_ne!(first[0], first[59]);
+    assert_eq!(play(&Loopback::default()), first);
+}
+
+#[test]
+fn paddles_stay_put_in_the_lobby() {
+    let loopback = Loopback::default();
+    let mut apps = vec![server(&loopback), client(&loopback, "Guest")];
+    step(&mut apps, 10);
+    for sequence in 0..10 {
+        apps[1].world_mut().send_event(PaddleInput {
+            sequence,
+            direction: 1.0,
+            dt: 1.0 / 60.0,
+        });
+        step(&mut apps, 1);
+    }
+    step(&mut apps, 5);
+    assert_eq!(paddle(&mut apps[0], 2).0.y, 50.0);
+}
+
+#[test]
+fn flooding_inputs_moves_a_paddle_no_faster() {
+    let loopback = Loopback::default();
+    let mut apps = vec![server(&loopback), client(&loopback, "Guest")];
+    step(&mut apps, 10);
+    for app in &mut apps {
+        app.world_mut().send_event(SetReady(true));
+    }
+    step(&mut apps, 10);
+    // Ahead of anything the client predicts, so none are dropped as late
+    for sequence in 1000..1120 {
+        apps[1].world_mut().send_event(PaddleInput {
+            sequence,
+            direction: 1.0,
+            dt: 0.1,
+        });
+    }
+    step(&mut apps, 5);
+    let y = paddle(&mut apps[0], 2).0.y;
+    let most = 50.0 + 80.0 * (prediction::INPUT_SLACK + 5.0 / 60.0);
+    assert!(y > 50.0 && y <= most, "{y}");
+}
+
+fn phase(app: &App) -> GamePhase {
+    app.world().resource::<State<GamePhase>>().get().clone()
+}
+
+#[test]
+fn clients_follow_the_match_to_its_results() {
+    let loopback = Loopback::default();
+    let mut apps = vec![server(&loopback), client(&loopback, "Guest")];
+    step(&mut apps, 10);
+    for app in &mut apps {
+        app.world_mut().send_event(SetReady(true));
+    }
+    step(&mut apps, 60);
+    assert_eq!(phase(&apps[1]), GamePhase::Rally);
+
+    apps[0].insert_resource(MatchWinner(Scorer::Ai));
+    apps[0]
+        .world_mut()
+        .resource_mut::<NextState<GamePhase>>()
+        .set(GamePhase::GameOver);
+    step(&mut apps, 2);
+    assert_eq!(phase(&apps[1]), GamePhase::GameOver);
+    assert_eq!(
+        apps[1].world().get_resource::<MatchWinner>(),
+        Some(&MatchWinner(Scorer::Ai))
+    );
+    let mut menus = apps[1].world_mut().query::<&MenuButton>();
+    assert!(menus.iter(apps[1].world()).count() > 0);
+
+    // A rematch on the server takes the client along
+    apps[0].world_mut().send_event(RestartMatch);
+    step(&mut apps, 5);
+    assert_eq!(phase(&apps[1]), GamePhase::Starting);
+    assert!(!apps[1].world().contains_resource::<MatchWinner>());
+    assert_eq!(menus.iter(apps[1].world()).count(), 0);
+}
+
+#[test]
+fn disconnecting_hands_the_paddle_back_to_the_computer() {
+    let loopback = Loopback::default();
+    let mut apps = vec![server(&loopback), client(&loopback, "Guest")];
+    step(&mut apps, 10);
+    let right = paddle_entity(&mut apps[0], 2);
+    assert!(apps[0].world().get::<AiController>(right).is_none());
+
+    apps[1].world().resource::<LoopbackClient>().disconnect();
+    step(&mut apps, 2);
+    assert!(apps[0].world().get::<AiController>(right).is_some());
+    assert_eq!(lobby(&mut apps[0]).len(), 1);
+}
+
+#[test]
+fn clients_get_through_a_bad_connection() {
+    let loopback = Loopback::default();
+    let mut apps = vec![
+        server(&loopback),
+        conditioned_app(
+            NetRole::Client(ClientSettings {
+                transport: Transport::Loopback(loopback.clone()),
+                ..default()
+            }),
+            "Guest",
+            NetworkConditions {
+                all: "latency=50,jitter=20,loss=20,duplication=5,reordering=10"
+                    .parse()
+                    .unwrap(),
+                seed: Some(1),
+                ..default()
+            },
+        ),
+    ];
+    // Nothing gets through before the latency has passed
+    step(&mut apps, 2);
+    assert!(lobby(&mut apps[1]).is_empty());
+
+    step(&mut apps, 60);
+    assert_eq!(lobby(&mut apps[1]).len(), 2);
+    assert_eq!(*apps[1].world().resource::<LocalPaddle>(), LocalPaddle(2));
+}
+
+/// Moves the client's paddle up for a third of a second over a connection
+/// with `conditions` and gives the server's answers time to arrive,
+/// returning where the client and the server put the paddle and how often
+/// the client had to rewind.
+fn steer_through(conditions: &str) -> (f32, f32, u32) {
+    let loopback = Loopback::default();
+    let mut apps = vec![
+        server(&loopback),
+        conditioned_app(
+            NetRole::Client(ClientSettings {
+                transport: Transport::Loopback(loopback.clone()),
+                ..default()
+            }),
+            "Guest",
+            NetworkConditions {
+                all: conditions.parse().unwrap(),
+                seed: Some(1),
+                ..default()
+            },
+        ),
+    ];
+    step(&mut apps, 60);
+    for app in &mut apps {
+        app.world_mut().send_event(SetReady(true));
+    }
+    step(&mut apps, 30);
+    let mut keys = apps[1].world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    keys.press(KeyCode::ArrowUp);
+    step(&mut apps, 20);
+    let mut keys = apps[1].world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    keys.release(KeyCode::ArrowUp);
+    step(&mut apps, 60);
+
+    let entity = paddle_entity(&mut apps[1], 2);
+    let rewinds = apps[1].world().get::<Prediction>(entity).unwrap().rewinds();
+    (
+        paddle(&mut apps[1], 2).0.y,
+        paddle(&mut apps[0], 2).0.y,
+        rewinds,
+    )
+}
+
+#[test]
+fn predicted_paddles_settle_where_the_server_has_them() {
+    let (client, server, rewinds) = steer_through("latency=50,jitter=10");
+    assert!(server > 60.0, "{server}");
+    assert!((client - server).abs() <= prediction::RECONCILE_TOLERANCE);
+    assert_eq!(rewinds, 0);
+
+    // Lost inputs never move the server's paddle, so the client has to take
+    // its word for where it is
+    let (client, server, rewinds) = steer_through("latency=50,jitter=10,loss=20");
+    assert!(server > 60.0, "{server}");
+    assert!((client - server).abs() <= prediction::RECONCILE_TOLERANCE);
+    assert!(rewinds > 0);
+}
+
+#[test]
+fn rejections_get_through_a_slow_connection() {
+    let loopback = Loopback::default();
+    let mut apps = vec![
+        conditioned_app(
+            NetRole::Server(ServerSettings {
+                transport: Transport::Loopback(loopback.clone()),
+                max_clients: 0,
+                ..default()
+            }),
+            "Host",
+            NetworkConditions {
+                all: "latency=800".parse().unwrap(),
+                ..default()
+            },
+        ),
+        client(&loopback, "Guest"),
+    ];
+    step(&mut apps, 120);
+    let reason = &apps[1].world().resource::<ConnectionError>().0;
+    assert!(reason.contains("full"), "{reason}");
+}
+
+#[test]
+fn diagnostics_measure_the_connection() {
+    let loopback = Loopback::default();
+    let mut apps = vec![
+        server(&loopback),
+        conditioned_app(
+            NetRole::Client(ClientSettings {
+                transport: Transport::Loopback(loopback.clone()),
+                ..default()
+            }),
+            "Guest",
+            NetworkConditions {
+                all: "latency=50".parse().unwrap(),
+                ..default()
+            },
+        ),
+    ];
+    // Long enough for the server to measure a round trip, pass it on with the
+    // next ping, and for the stats to roll over after that
+    step(&mut apps, 240);
+
+    let stats = apps[1].world().resource::<NetStats>();
+    let rtt = stats.rtt.unwrap();
+    assert!(rtt >= Duration::from_millis(100), "{rtt:?}");
+    assert!(stats.replicated_entities >= 3);
+    assert!(stats.bytes_received_per_second > 0.0);
+    assert!(stats.channels.iter().any(
+        |(channel, stats)| matches!(channel, ChannelId::ToServer(_)) && stats.messages > 0
+    ));
+    assert!(stats.events.iter().any(|event| event.contains("Connected")));
+
+    let mut query = apps[0].world_mut().query::<&ClientNetStats>();
+    let client = query.single(apps[0].world()).unwrap();
+    assert!(client.rtt.unwrap() >= Duration::from_millis(100));
+    assert!(client.bytes_sent > 0 && client.bytes_received > 0);
+
+    let store = apps[1].world().resource::<DiagnosticsStore>();
+    let measured = store
+        .get(&NetworkDiagnosticsPlugin::RTT)
+        .and_then(|rtt| rtt.value())
+        .unwrap();
+    assert!(measured >= 100.0, "{measured}");
+}
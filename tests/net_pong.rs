@@ -0,0 +1,312 @@
+//! Runs a server and a client in the same process, talking over localhost.
+
+use std::{
+    net::{Ipv6Addr, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_quinnet::client::QuinnetClient;
+use bevy_replicon::prelude::ConnectedClient;
+use common::*;
+use first_bevy_game::{net::*, pong::*};
+
+mod common;
+
+fn net_app(role: NetRole, name: &str) -> App {
+    app_with(NetPongPlugin {
+        role,
+        pong: PongConfig {
+            seed: Some(3),
+            start_delay: 0.5,
+            ..default()
+        },
+        player: PlayerInfo {
+            name: name.to_string(),
+            color: [243, 139, 168],
+        },
+        ..default()
+    })
+}
+
+/// A port nothing else is listening on right now.
+fn free_port() -> u16 {
+    UdpSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn client_app(port: u16, name: &str) -> App {
+    pinned_client_app(port, name, None)
+}
+
+/// A client expecting `trust` if given.
+fn pinned_client_app(port: u16, name: &str, trust: Option<String>) -> App {
+    net_app(
+        NetRole::Client(ClientSettings {
+            port,
+            trust,
+            ..default()
+        }),
+        name,
+    )
+}
+
+fn server_pair(settings: ServerSettings) -> (App, App) {
+    let server = net_app(NetRole::Server(settings.clone()), "Host");
+    (server, client_app(settings.port, "Guest"))
+}
+
+fn server_settings() -> ServerSettings {
+    ServerSettings {
+        port: free_port(),
+        ..default()
+    }
+}
+
+fn connected_pair() -> (App, App) {
+    server_pair(server_settings())
+}
+
+/// Updates both apps in real time until `done` holds, giving up after five
+/// seconds.
+fn run_until(server: &mut App, client: &mut App, mut done: impl FnMut(&mut App, &mut App) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done(server, client) {
+        assert!(Instant::now() < deadline, "timed out");
+        server.update();
+        client.update();
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn count<C: Component>(app: &mut App) -> usize {
+    let mut query = app.world_mut().query::<&C>();
+    query.iter(app.world()).count()
+}
+
+fn net_state(app: &App) -> NetState {
+    *app.world().resource::<State<NetState>>().get()
+}
+
+fn lobby(app: &mut App) -> Vec<LobbyPlayer> {
+    let mut query = app.world_mut().query::<&LobbyPlayer>();
+    let mut players: Vec<_> = query.iter(app.world()).cloned().collect();
+    players.sort_by_key(|player| player.slot);
+    players
+}
+
+/// Readies up both ends and waits for the match to start on the client.
+fn ready_up(server: &mut App, client: &mut App) {
+    server.world_mut().send_event(SetReady(true));
+    client.world_mut().send_event(SetReady(true));
+    run_until(server, client, |_, client| {
+        net_state(client) == NetState::InGame
+    });
+}
+
+#[test]
+fn client_sees_the_match_and_steers_its_paddle() {
+    let (mut server, mut client) = connected_pair();
+    run_until(&mut server, &mut client, |_, client| {
+        count::<Ball>(client) == 1
+            && count::<Paddle>(client) == 2
+            && count::<Prediction>(client) == 1
+    });
+    assert_eq!(*client.world().resource::<LocalPaddle>(), LocalPaddle(2));
+    ready_up(&mut server, &mut client);
+
+    // The served ball moves on the client too
+    let start = ball(&mut client).0;
+    run_until(&mut server, &mut client, |_, client| {
+        ball(client).0 != start
+    });
+
+    client
+        .world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::ArrowUp);
+    // Predicted on the client without waiting for the server to update
+    let before = paddle(&mut client, 2);
+    thread::sleep(Duration::from_millis(5));
+    client.update();
+    assert!(paddle(&mut client, 2).0.y > before.0.y);
+    run_until(&mut server, &mut client, |server, client| {
+        paddle(server, 2).0.y > 60.0 && paddle(client, 2).0.y > 60.0
+    });
+    // The host's paddle didn't move
+    assert_eq!(paddle(&mut server, 1).0.y, 50.0);
+}
+
+#[test]
+fn match_waits_in_the_lobby_until_everyone_is_ready() {
+    let (mut server, mut client) = connected_pair();
+    run_until(&mut server, &mut client, |_, client| {
+        lobby(client).len() == 2
+    });
+    let players = lobby(&mut client);
+    assert_eq!(players[0].info.name, "Host");
+    assert_eq!(players[0].slot, Some(1));
+    assert_eq!(players[1].info.name, "Guest");
+    assert_eq!(players[1].slot, Some(2));
+    assert_eq!(players[1].info.color, [243, 139, 168]);
+
+    // Only the host is ready, so the ball stays put
+    let start = ball(&mut server).0;
+    server.world_mut().send_event(SetReady(true));
+    run_until(&mut server, &mut client, |_, client| lobby(client)[0].ready);
+    for _ in 0..50 {
+        server.update();
+        client.update();
+    }
+    assert_eq!(net_state(&server), NetState::Lobby);
+    assert_eq!(ball(&mut server).0, start);
+
+    client.world_mut().send_event(SetReady(true));
+    run_until(&mut server, &mut client, |server, client| {
+        net_state(server) == NetState::InGame
+            && net_state(client) == NetState::InGame
+            && ball(server).0 != start
+    });
+    let mut screens = client.world_mut().query::<&LobbyList>();
+    assert_eq!(screens.iter(client.world()).count(), 0);
+}
+
+#[test]
+fn dedicated_servers_leave_both_paddles_to_clients() {
+    let (mut server, mut client) = server_pair(ServerSettings {
+        dedicated: true,
+        ..server_settings()
+    });
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().contains_resource::<LocalPaddle>()
+    });
+    assert_eq!(*client.world().resource::<LocalPaddle>(), LocalPaddle(1));
+    assert!(!server.world().contains_resource::<LocalPaddle>());
+    let players = lobby(&mut server);
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0].info.name, "Guest");
+
+    // The client alone is enough to start
+    client.world_mut().send_event(SetReady(true));
+    run_until(&mut server, &mut client, |_, client| {
+        net_state(client) == NetState::InGame
+    });
+}
+
+#[test]
+fn full_servers_turn_clients_away() {
+    let settings = ServerSettings {
+        max_clients: 1,
+        ..server_settings()
+    };
+    let port = settings.port;
+    let (mut server, mut client) = server_pair(settings);
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().contains_resource::<LocalPaddle>()
+    });
+
+    let mut late = client_app(port, "Late");
+    for _ in 0..200 {
+        server.update();
+        client.update();
+        late.update();
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(count::<ConnectedClient>(&mut server), 1);
+    assert_eq!(lobby(&mut server).len(), 2);
+    assert!(!late.world().contains_resource::<LocalPaddle>());
+    assert!(
+        late.world()
+            .resource::<ConnectionError>()
+            .0
+            .contains("full")
+    );
+}
+
+#[test]
+fn clients_of_another_version_are_told_why_they_are_turned_away() {
+    let (mut server, mut client) = connected_pair();
+    client.world_mut().resource_mut::<Protocol>().version += 1;
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().contains_resource::<ConnectionError>()
+    });
+    let reason = &client.world().resource::<ConnectionError>().0;
+    assert!(reason.contains("protocol version"), "{reason}");
+
+    for _ in 0..200 {
+        server.update();
+        client.update();
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(count::<ConnectedClient>(&mut server), 0);
+    // Nothing was replicated to it and it never joined the lobby
+    assert_eq!(count::<Ball>(&mut client), 0);
+    assert_eq!(lobby(&mut server).len(), 1);
+}
+
+#[test]
+fn score_reaches_the_client() {
+    let (mut server, mut client) = connected_pair();
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().contains_resource::<LocalPaddle>()
+    });
+
+    server.world_mut().resource_mut::<Score>().ai = 3;
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().resource::<Score>().ai == 3
+    });
+}
+
+#[test]
+fn leaving_hands_the_paddle_back_to_the_computer() {
+    let (mut server, mut client) = connected_pair();
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().contains_resource::<LocalPaddle>()
+    });
+    let right = paddle_entity(&mut server, 2);
+    assert!(server.world().get::<AiController>(right).is_none());
+
+    client
+        .world_mut()
+        .resource_mut::<QuinnetClient>()
+        .close_all_connections();
+    run_until(&mut server, &mut client, |server, _| {
+        server.world().get::<AiController>(right).is_some()
+    });
+    assert!(server.world().get::<RemoteControlled>(right).is_none());
+    // Their place in the lobby is free for the next client
+    assert_eq!(lobby(&mut server).len(), 1);
+}
+
+#[test]
+fn clients_refuse_servers_not_matching_the_pinned_fingerprint() {
+    let settings = server_settings();
+    let mut server = net_app(NetRole::Server(settings.clone()), "Host");
+    let mut client = pinned_client_app(settings.port, "Guest", Some("bm90IGl0".to_string()));
+    run_until(&mut server, &mut client, |_, client| {
+        client.world().contains_resource::<ConnectionError>()
+    });
+    assert!(
+        client
+            .world()
+            .resource::<ConnectionError>()
+            .0
+            .contains("didn't present the certificate you asked to trust"),
+        "{:?}",
+        client.world().resource::<ConnectionError>()
+    );
+    for _ in 0..50 {
+        server.update();
+        client.update();
+    }
+    assert!(!client.world().contains_resource::<LocalPaddle>());
+    assert!(
+        lobby(&mut server)
+            .iter()
+            .all(|player| player.info.name != "Guest")
+    );
+}
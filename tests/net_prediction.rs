@@ -0,0 +1,203 @@
+//! Plays a client's paddle against the server's copy over a loopback link
+//! that delays and drops messages, one frame at a time.
+
+use std::collections::VecDeque;
+
+use first_bevy_game::net::{prediction::RECONCILE_TOLERANCE, *};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+const DT: f32 = 1.0 / 60.0;
+const SPEED: f32 = 80.0;
+
+/// Delivers messages `latency` frames after they are sent, losing each one
+/// with probability `loss`.
+struct Link<T> {
+    latency: u32,
+    loss: f64,
+    rng: StdRng,
+    in_flight: VecDeque<(u32, T)>,
+}
+
+impl<T> Link<T> {
+    fn new(latency: u32, loss: f64, seed: u64) -> Self {
+        Self {
+            latency,
+            loss,
+            rng: StdRng::seed_from_u64(seed),
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    fn send(&mut self, frame: u32, message: T) {
+        if !self.rng.random_bool(self.loss) {
+            self.in_flight.push_back((frame + self.latency, message));
+        }
+    }
+
+    fn receive(&mut self, frame: u32) -> Vec<T> {
+        let mut delivered = Vec::new();
+        while self.in_flight.front().is_some_and(|(at, _)| *at <= frame) {
+            delivered.push(self.in_flight.pop_front().unwrap().1);
+        }
+        delivered
+    }
+}
+
+/// A client predicting its paddle and the server moving it, connected by a
+/// link each way.
+struct Harness {
+    frame: u32,
+    client: Prediction,
+    server_y: f32,
+    server_last: LastInput,
+    up: Link<PaddleInput>,
+    down: Link<PaddleAck>,
+}
+
+impl Harness {
+    fn new(latency: u32, loss: f64) -> Self {
+        Self {
+            frame: 0,
+            client: Prediction::new(50.0),
+            server_y: 50.0,
+            server_last: LastInput::default(),
+            up: Link::new(latency, loss, 1),
+            down: Link::new(latency, loss, 2),
+        }
+    }
+
+    fn step(&mut self, direction: f32) {
+        let input = self.client.predict(direction, DT, SPEED);
+        self.up.send(self.frame, input);
+
+        let mut ack = None;
+        for input in self.up.receive(self.frame) {
+            if let Some(y) = self.server_last.apply(self.server_y, &input, SPEED) {
+                self.server_y = y;
+                ack = Some(PaddleAck {
+                    sequence: input.sequence,
+                    y,
+                });
+            }
+        }
+        if let Some(ack) = ack {
+            self.down.send(self.frame, ack);
+        }
+
+        for ack in self.down.receive(self.frame) {
+            self.client.reconcile(&ack, SPEED);
+        }
+        self.frame += 1;
+    }
+
+    /// Holds still until the server has caught up with every input.
+    fn settle(&mut self) {
+        for _ in 0..120 {
+            self.step(0.0);
+        }
+    }
+}
+
+#[test]
+fn paddle_moves_before_the_server_hears_of_it() {
+    let mut harness = Harness::new(6, 0.0);
+    harness.step(1.0);
+    assert!(harness.client.y() > 50.0);
+    assert_eq!(harness.server_y, 50.0);
+
+    for _ in 0..30 {
+        harness.step(1.0);
+    }
+    harness.settle();
+    assert_eq!(harness.client.y(), harness.server_y);
+    assert!(harness.server_y > 60.0);
+    // Nothing was lost, so the server never disagreed
+    assert_eq!(harness.client.rewinds(), 0);
+    assert!(harness.client.unacked() <= 12);
+}
+
+#[test]
+fn lost_inputs_are_corrected_by_the_server() {
+    let mut harness = Harness::new(6, 0.2);
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..300 {
+        harness.step(rng.random_range(-1.0..=1.0));
+    }
+    harness.settle();
+    assert!(harness.client.rewinds() > 0);
+    assert!((harness.client.y() - harness.server_y).abs() <= RECONCILE_TOLERANCE);
+}
+
+#[test]
+fn server_corrections_replay_unacked_inputs() {
+    let mut prediction = Prediction::new(50.0);
+    let inputs: Vec<_> = (0..3).map(|_| prediction.predict(1.0, DT, SPEED)).collect();
+
+    // The server had the paddle somewhere else when the first input arrived
+    prediction.reconcile(
+        &PaddleAck {
+            sequence: inputs[0].sequence,
+            y: 20.0,
+        },
+        SPEED,
+    );
+    let replayed = step_paddle(step_paddle(20.0, &inputs[1], SPEED), &inputs[2], SPEED);
+    assert_eq!(prediction.y(), replayed);
+    assert_eq!(prediction.unacked(), 2);
+    assert_eq!(prediction.rewinds(), 1);
+
+    // An ack overtaken by a newer one changes nothing
+    prediction.reconcile(
+        &PaddleAck {
+            sequence: inputs[0].sequence,
+            y: 90.0,
+        },
+        SPEED,
+    );
+    assert_eq!(prediction.y(), replayed);
+}
+
+#[test]
+fn server_drops_late_and_oversized_inputs() {
+    let mut last = LastInput::default();
+    let input = |sequence, dt| PaddleInput {
+        sequence,
+        direction: 1.0,
+        dt,
+    };
+    let y = last.apply(50.0, &input(1, DT), SPEED).unwrap();
+    assert_eq!(last.apply(y, &input(0, DT), SPEED), None);
+
+    // A client claiming a ten second frame only moves as far as the longest
+    // frame allowed
+    let jump = last.apply(50.0, &input(2, 10.0), SPEED).unwrap();
+    assert_eq!(jump, 50.0 + SPEED * prediction::MAX_INPUT_DT);
+    // and never past the edge of the playfield
+    assert_eq!(
+        last.apply(87.5, &input(3, DT), SPEED),
+        Some(prediction::PADDLE_MAX_Y)
+    );
+}
+
+#[test]
+fn inputs_only_move_paddles_for_as_long_as_the_server_ran() {
+    let mut budget = InputBudget::default();
+    let mut last = LastInput::default();
+    let mut y = 50.0;
+    budget.refill(DT);
+    // A whole second of frames claimed within one frame of server time
+    for sequence in 0..60 {
+        let input = budget.limit(&PaddleInput {
+            sequence,
+            direction: 1.0,
+            dt: DT,
+        });
+        y = last.apply(y, &input, SPEED).unwrap();
+        budget.spend(&input);
+    }
+    assert!((y - (50.0 + SPEED * DT)).abs() < 1e-4, "{y}");
+
+    // Time spent waiting for inputs can be made up for, but only so much
+    budget.refill(10.0);
+    assert_eq!(budget.0, prediction::INPUT_SLACK);
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use first_bevy_game::{net::*, pong::Position};
+
+#[derive(Event, serde::Serialize, serde::Deserialize)]
+struct Ping;
+
+#[derive(Event, serde::Serialize, serde::Deserialize)]
+struct Renamed;
+
+fn protocol(register: impl FnOnce(&mut App)) -> Protocol {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, RepliconPlugins));
+    register(&mut app);
+    *app.world().resource::<Protocol>()
+}
+
+#[test]
+fn matching_protocols_get_in() {
+    let server = protocol(|app| {
+        app.replicate_checked::<Position>("position", 1);
+    });
+    let client = protocol(|app| {
+        app.replicate_checked::<Position>("position", 1);
+    });
+    assert_eq!(server, client);
+    assert_eq!(server.version, PROTOCOL_VERSION);
+    assert_eq!(server.mismatch(&client), None);
+}
+
+#[test]
+fn anything_registered_differently_changes_the_hash() {
+    let base = protocol(|app| {
+        app.replicate_checked::<Position>("position", 1)
+            .add_client_event_checked::<Ping>("ping", 1, Channel::Ordered);
+    });
+    let others = [
+        protocol(|app| {
+            app.replicate_checked::<Position>("position", 1);
+        }),
+        protocol(|app| {
+            app.replicate_checked::<Position>("position", 2)
+                .add_client_event_checked::<Ping>("ping", 1, Channel::Ordered);
+        }),
+        protocol(|app| {
+            app.replicate_checked::<Position>("position", 1)
+                .add_client_event_checked::<Ping>("echo", 1, Channel::Ordered);
+        }),
+        protocol(|app| {
+            app.add_client_event_checked::<Ping>("ping", 1, Channel::Ordered)
+                .replicate_checked::<Position>("position", 1);
+        }),
+        protocol(|app| {
+            app.replicate_checked::<Position>("position", 1)
+                .add_client_event_checked::<Ping>("ping", 1, Channel::Unreliable);
+        }),
+        protocol(|app| {
+            app.replicate_checked::<Position>("position", 1)
+                .add_server_event_checked::<Ping>("ping", 1, Channel::Ordered);
+        }),
+    ];
+    for other in others {
+        assert_ne!(other.hash, base.hash);
+        assert!(base.mismatch(&other).unwrap().contains("another version"));
+    }
+}
+
+#[test]
+fn rust_names_stay_out_of_the_hash() {
+    let ping = protocol(|app| {
+        app.add_client_event_checked::<Ping>("ping", 1, Channel::Ordered);
+    });
+    let renamed = protocol(|app| {
+        app.add_client_event_checked::<Renamed>("ping", 1, Channel::Ordered);
+    });
+    assert_eq!(ping, renamed);
+}
+
+#[test]
+#[should_panic(expected = "registered with the protocol twice")]
+fn names_are_unique() {
+    protocol(|app| {
+        app.add_client_event_checked::<Ping>("ping", 1, Channel::Ordered)
+            .add_server_event_checked::<Renamed>("ping", 1, Channel::Ordered);
+    });
+}
+
+#[test]
+fn version_mismatches_say_which_versions() {
+    let server = Protocol::default();
+    let client = Protocol {
+        version: PROTOCOL_VERSION + 1,
+        ..server
+    };
+    let reason = server.mismatch(&client).unwrap();
+    assert!(
+        reason.contains(&format!("version {PROTOCOL_VERSION}")),
+        "{reason}"
+    );
+    assert!(
+        reason.contains(&format!("version {}", PROTOCOL_VERSION + 1)),
+        "{reason}"
+    );
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use common::*;
+use first_bevy_game::pong::*;
+
+mod common;
+
+fn seeded() -> PongConfig {
+    PongConfig {
+        seed: Some(7),
+        ..default()
+    }
+}
+
+fn phase(app: &App) -> GamePhase {
+    app.world().resource::<State<GamePhase>>().get().clone()
+}
+
+#[test]
+fn spawns_ball_and_paddles() {
+    let mut app = headless_app(seeded());
+
+    assert_eq!(ball(&mut app).0, Position(Vec2::new(50.0, 50.0)));
+    assert_eq!(paddle(&mut app, 1), Position(Vec2::new(10.0, 50.0)));
+    assert_eq!(paddle(&mut app, 2), Position(Vec2::new(90.0, 50.0)));
+}
+
+#[test]
+fn without_simulation_only_received_entities_move() {
+    let mut app = headless_app(PongConfig {
+        simulate: false,
+        start_delay: 0.0,
+        ..seeded()
+    });
+    let mut balls = app.world_mut().query::<&Ball>();
+    assert_eq!(balls.iter(app.world()).count(), 0);
+    let mut paddles = app.world_mut().query::<&Paddle>();
+    assert_eq!(paddles.iter(app.world()).count(), 0);
+
+    // A ball arriving from elsewhere is drawn where it is put, not moved
+    app.world_mut().spawn((
+        Ball,
+        Position(Vec2::new(30.0, 40.0)),
+        Velocity(Vec2::new(40.0, 0.0)),
+    ));
+    run_for(&mut app, Duration::from_secs(1));
+    assert_eq!(ball(&mut app).0, Position(Vec2::new(30.0, 40.0)));
+    assert_eq!(phase(&app), GamePhase::Starting);
+
+    // The match moves on when whatever runs it says so, and only it can start
+    // a rematch
+    app.insert_resource(MatchWinner(Scorer::Ai));
+    app.world_mut()
+        .resource_mut::<NextState<GamePhase>>()
+        .set(GamePhase::GameOver);
+    app.update();
+    let mut buttons = app.world_mut().query::<&MenuButton>();
+    let buttons: Vec<_> = buttons.iter(app.world()).copied().collect();
+    assert_eq!(buttons, [MenuButton::Quit]);
+}
+
+#[test]
+fn ball_waits_for_start_delay() {
+    let mut app = headless_app(seeded());
+
+    run_for(&mut app, Duration::from_secs(1));
+    assert_eq!(phase(&app), GamePhase::Starting);
+    assert_eq!(ball(&mut app).0, Position(Vec2::new(50.0, 50.0)));
+
+    run_for(&mut app, Duration::from_millis(1500));
+    assert_eq!(phase(&app), GamePhase::Rally);
+    assert_ne!(ball(&mut app).0, Position(Vec2::new(50.0, 50.0)));
+}
+
+#[test]
+fn serve_uses_configured_speed() {
+    let mut app = headless_app(PongConfig {
+        ball_speed: 25.0,
+        ..seeded()
+    });
+
+    assert_eq!(ball(&mut app).1.0.x.abs(), 25.0);
+}
+
+#[test]
+fn player_paddle_follows_input_and_stops_at_edge() {
+    let mut app = headless_app(PongConfig {
+        start_delay: 0.0,
+        ..seeded()
+    });
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::ArrowUp);
+
+    run_for(&mut app, Duration::from_millis(100));
+    assert!(paddle(&mut app, 1).0.y > 50.0);
+
+    run_for(&mut app, Duration::from_secs(2));
+    let y = paddle(&mut app, 1).0.y;
+    assert!((88.0..92.0).contains(&y), "paddle escaped to {y}");
+}
+
+#[test]
+fn ball_bounces_off_barrier() {
+    let mut app = headless_app(PongConfig {
+        start_delay: 0.0,
+        ..seeded()
+    });
+    app.update();
+    set_ball(&mut app, Vec2::new(50.0, 95.0), Vec2::new(0.0, 40.0));
+
+    run_for(&mut app, Duration::from_millis(500));
+    let (position, velocity) = ball(&mut app);
+    assert!(velocity.0.y < 0.0);
+    assert!(position.0.y < 100.0);
+}
+
+#[test]
+fn scoring_updates_score_and_resets_ball() {
+    let mut app = headless_app(PongConfig {
+        start_delay: 0.0,
+        ..seeded()
+    });
+    app.update();
+    set_ball(&mut app, Vec2::new(99.5, 50.0), Vec2::new(60.0, 0.0));
+
+    run_for(&mut app, Duration::from_millis(100));
+    let score = app.world().resource::<Score>();
+    assert_eq!((score.player, score.ai), (1, 0));
+    assert_eq!(phase(&app), GamePhase::Scoring);
+    assert_eq!(
+        ball(&mut app),
+        (Position(Vec2::new(50.0, 50.0)), Velocity(Vec2::ZERO))
+    );
+
+    // Served towards the side that conceded once the break is over.
+    run_for(&mut app, Duration::from_secs(1));
+    assert_eq!(phase(&app), GamePhase::Rally);
+    assert!(ball(&mut app).1.0.x > 0.0);
+}
+
+fn run_ticks(app: &mut App, frame: Duration, ticks: u32) {
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(frame));
+    let step = app.world().resource::<Time<Fixed>>().timestep();
+    while app.world().resource::<Time<Fixed>>().elapsed() < step * ticks {
+        app.update();
+    }
+    assert_eq!(
+        app.world().resource::<Time<Fixed>>().elapsed(),
+        step * ticks
+    );
+}
+
+fn positions(app: &mut App) -> Vec<(String, Position)> {
+    let mut query = app.world_mut().query::<(&Name, &Position)>();
+    let mut positions: Vec<_> = query
+        .iter(app.world())
+        .map(|(name, position)| (name.to_string(), *position))
+        .collect();
+    positions.sort_by(|a, b| a.0.cmp(&b.0));
+    positions
+}
+
+#[test]
+fn simulation_is_independent_of_frame_rate() {
+    let config = PongConfig {
+        start_delay: 0.5,
+        ..seeded()
+    };
+    let mut apps = [
+        Duration::from_millis(7),
+        Duration::from_millis(10),
+        Duration::from_micros(31_250),
+    ]
+    .map(|frame| {
+        let mut app = headless_app(config.clone());
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::ArrowUp);
+        run_ticks(&mut app, frame, 200);
+        app
+    });
+
+    let expected = positions(&mut apps[0]);
+    assert_eq!(expected.len(), 3);
+    for app in &mut apps[1..] {
+        assert_eq!(positions(app), expected);
+    }
+}
+
+#[test]
+fn drag_is_independent_of_tick_rate() {
+    let velocities = [30.0, 64.0, 120.0].map(|tick_rate| {
+        let mut app = headless_app(PongConfig {
+            start_delay: 0.0,
+            tick_rate,
+            ..seeded()
+        });
+        while phase(&app) != GamePhase::Rally {
+            app.update();
+        }
+        let mut query = app.world_mut().query::<(&Paddle, &mut Velocity)>();
+        for (_, mut velocity) in query.iter_mut(app.world_mut()) {
+            velocity.0 = Vec2::new(0.0, 80.0);
+        }
+        let start = app.world().resource::<Time<Fixed>>().elapsed();
+        let ticks = (0.5 * tick_rate) as u32;
+        let step = app.world().resource::<Time<Fixed>>().timestep();
+        while app.world().resource::<Time<Fixed>>().elapsed() - start < step * ticks {
+            app.update();
+        }
+        let mut query = app.world_mut().query::<(&Paddle, &Velocity)>();
+        query
+            .iter(app.world())
+            .find(|(paddle, _)| paddle.player == 1)
+            .map(|(_, velocity)| velocity.0.y)
+            .unwrap()
+    });
+
+    for velocity in velocities {
+        assert!((velocity - velocities[0]).abs() < 0.5, "{velocities:?}");
+    }
+}
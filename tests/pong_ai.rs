@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use common::*;
+use first_bevy_game::pong::*;
+
+mod common;
+
+#[test]
+fn predicts_straight_intercept() {
+    let y = predict_intercept(
+        Vec2::new(50.0, 50.0),
+        Vec2::new(40.0, 10.0),
+        90.0,
+        2.0,
+        98.0,
+    );
+    assert_eq!(y, Some(60.0));
+}
+
+#[test]
+fn predicts_intercept_after_bounces() {
+    // Climbs 160 units: 48 up to the top, 96 down to the bottom and 16 back up.
+    let y = predict_intercept(
+        Vec2::new(50.0, 50.0),
+        Vec2::new(-40.0, 160.0),
+        10.0,
+        2.0,
+        98.0,
+    );
+    assert!((y.unwrap() - 18.0).abs() < 1e-4, "{y:?}");
+
+    let y = predict_intercept(
+        Vec2::new(50.0, 10.0),
+        Vec2::new(40.0, -20.0),
+        90.0,
+        2.0,
+        98.0,
+    );
+    assert!((y.unwrap() - 14.0).abs() < 1e-4, "{y:?}");
+}
+
+#[test]
+fn no_intercept_when_moving_away() {
+    let y = predict_intercept(
+        Vec2::new(50.0, 50.0),
+        Vec2::new(-40.0, 10.0),
+        90.0,
+        2.0,
+        98.0,
+    );
+    assert_eq!(y, None);
+    let y = predict_intercept(Vec2::new(50.0, 50.0), Vec2::new(0.0, 10.0), 90.0, 2.0, 98.0);
+    assert_eq!(y, None);
+}
+
+/// Plays `left` against `right` for `seconds` of game time and returns the
+/// points each side scored.
+fn play(left: Difficulty, right: Difficulty, seconds: u32, seed: u64) -> (u32, u32) {
+    let mut app = headless_app(PongConfig {
+        start_delay: 0.0,
+        difficulty: right,
+        seed: Some(seed),
+        // Keep playing for the whole duration
+        rules: MatchRules {
+            points_to_win: u32::MAX,
+            ..default()
+        },
+        ..default()
+    });
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        100,
+    )));
+
+    let left_paddle = paddle_entity(&mut app, 1);
+    app.world_mut()
+        .entity_mut(left_paddle)
+        .insert(AiController::new(left));
+
+    run_for(&mut app, Duration::from_secs(seconds.into()));
+    let score = app.world().resource::<Score>();
+    (score.player, score.ai)
+}
+
+#[test]
+fn ai_moves_at_paddle_speed() {
+    let mut app = headless_app(PongConfig {
+        start_delay: 0.0,
+        difficulty: Difficulty::Hard,
+        seed: Some(3),
+        ..default()
+    });
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        10,
+    )));
+
+    let mut query = app.world_mut().query::<(&Position, &Paddle, &Speed)>();
+    let mut last = None;
+    for _ in 0..200 {
+        app.update();
+        let (position, _, speed) = query
+            .iter(app.world())
+            .find(|(_, paddle, _)| paddle.player == 2)
+            .unwrap();
+        if let Some(last) = last {
+            let moved: f32 = position.0.y - last;
+            assert!(moved.abs() <= speed.0 * 0.02 + 1e-3, "teleported {moved}");
+        }
+        assert!((11.0..=89.0).contains(&position.0.y));
+        last = Some(position.0.y);
+    }
+}
+
+#[test]
+fn easy_loses_more_than_hard() {
+    let mut easy_points = 0;
+    let mut hard_points = 0;
+    for seed in 0..2 {
+        let (left, right) = play(Difficulty::Easy, Difficulty::Hard, 45, seed);
+        easy_points += left;
+        hard_points += right;
+        let (left, right) = play(Difficulty::Hard, Difficulty::Easy, 45, seed);
+        hard_points += left;
+        easy_points += right;
+    }
+
+    assert!(
+        hard_points >= easy_points * 2 + 5,
+        "hard scored {hard_points}, easy scored {easy_points}"
+    );
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use bevy::{math::bounding::Aabb2d, prelude::*, time::TimeUpdateStrategy};
+use common::*;
+use first_bevy_game::pong::*;
+
+mod common;
+
+fn ball_box(center: Vec2) -> Aabb2d {
+    Aabb2d::new(center, Vec2::splat(2.0))
+}
+
+fn paddle_box(center: Vec2) -> Aabb2d {
+    Aabb2d::new(center, Vec2::new(2.0, 10.0))
+}
+
+#[test]
+fn sweep_finds_time_of_impact() {
+    let contact = sweep(
+        ball_box(Vec2::new(50.0, 50.0)),
+        Vec2::new(-40.0, 0.0),
+        paddle_box(Vec2::new(10.0, 50.0)),
+    )
+    .unwrap();
+
+    // The ball's left edge (48) meets the paddle's right edge (12) after 36
+    // units.
+    assert!((contact.time - 36.0 / 40.0).abs() < 1e-5);
+    assert_eq!(contact.side, Collision::Right);
+    assert_eq!(contact.depth, 0.0);
+}
+
+#[test]
+fn sweep_does_not_tunnel_at_high_speed() {
+    // Travels 100 units in a single step through a paddle that is 4 units wide.
+    let contact = sweep(
+        ball_box(Vec2::new(60.0, 50.0)),
+        Vec2::new(-100.0, 3.0),
+        paddle_box(Vec2::new(10.0, 50.0)),
+    )
+    .unwrap();
+
+    assert_eq!(contact.side, Collision::Right);
+    assert!((contact.time - 0.46).abs() < 1e-5);
+}
+
+#[test]
+fn sweep_does_not_tunnel_through_barrier() {
+    let barrier = Aabb2d::new(Vec2::new(50.0, 102.5), Vec2::new(50.0, 2.5));
+    let contact = sweep(
+        ball_box(Vec2::new(50.0, 90.0)),
+        Vec2::new(5.0, 500.0),
+        barrier,
+    )
+    .unwrap();
+
+    assert_eq!(contact.side, Collision::Bottom);
+    assert!((contact.time - 8.0 / 500.0).abs() < 1e-5);
+}
+
+#[test]
+fn sweep_misses_when_passing_by_or_moving_away() {
+    let paddle = paddle_box(Vec2::new(10.0, 50.0));
+
+    assert_eq!(
+        sweep(
+            ball_box(Vec2::new(50.0, 80.0)),
+            Vec2::new(-100.0, 0.0),
+            paddle
+        ),
+        None
+    );
+    assert_eq!(
+        sweep(
+            ball_box(Vec2::new(50.0, 50.0)),
+            Vec2::new(100.0, 0.0),
+            paddle
+        ),
+        None
+    );
+    assert_eq!(
+        sweep(
+            ball_box(Vec2::new(50.0, 50.0)),
+            Vec2::new(-10.0, 0.0),
+            paddle
+        ),
+        None
+    );
+}
+
+#[test]
+fn sweep_reports_existing_overlap() {
+    let contact = sweep(
+        ball_box(Vec2::new(13.0, 50.0)),
+        Vec2::new(-1.0, 0.0),
+        paddle_box(Vec2::new(10.0, 50.0)),
+    )
+    .unwrap();
+
+    assert_eq!(contact.time, 0.0);
+    assert_eq!(contact.side, Collision::Right);
+    assert!((contact.depth - 1.0).abs() < 1e-5);
+}
+
+/// A match already in its rally, with the ball served.
+fn rally_app() -> App {
+    let mut app = headless_app(PongConfig {
+        start_delay: 0.0,
+        seed: Some(1),
+        ..default()
+    });
+    app.update();
+    app
+}
+
+#[test]
+fn fast_ball_bounces_off_paddle() {
+    let mut app = rally_app();
+    let y = paddle(&mut app, 1).0.y;
+    set_ball(&mut app, Vec2::new(40.0, y), Vec2::new(-3000.0, 0.0));
+
+    app.update();
+    let (position, velocity) = ball(&mut app);
+    assert!(velocity.0.x > 0.0, "ball tunneled to {position:?}");
+    assert!(position.0.x > 12.0);
+}
+
+#[test]
+fn long_frame_does_not_tunnel() {
+    let mut app = rally_app();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        500,
+    )));
+    let y = paddle(&mut app, 1).0.y;
+    set_ball(&mut app, Vec2::new(30.0, y), Vec2::new(-100.0, 0.0));
+
+    app.update();
+    let (position, velocity) = ball(&mut app);
+    assert!(velocity.0.x > 0.0, "ball tunneled to {position:?}");
+    assert!(position.0.x > 12.0);
+}
+
+#[test]
+fn fast_ball_stays_between_barriers() {
+    let mut app = rally_app();
+    set_ball(&mut app, Vec2::new(50.0, 50.0), Vec2::new(0.0, 5000.0));
+
+    for _ in 0..10 {
+        app.update();
+        let (position, _) = ball(&mut app);
+        assert!(
+            (2.0..=98.0).contains(&position.0.y),
+            "ball escaped to {position:?}"
+        );
+    }
+}
+
+#[test]
+fn ball_out_of_contacts_stays_between_barriers() {
+    let mut app = rally_app();
+    // Fast enough to bounce off the barriers more often in a step than is
+    // resolved
+    set_ball(&mut app, Vec2::new(50.0, 50.0), Vec2::new(0.0, 60000.0));
+
+    for _ in 0..10 {
+        app.update();
+        let (position, _) = ball(&mut app);
+        assert!(
+            (2.0..=98.0).contains(&position.0.y),
+            "ball escaped to {position:?}"
+        );
+    }
+}
+
+#[test]
+fn overlapping_ball_reflects_once() {
+    let mut app = rally_app();
+    let y = paddle(&mut app, 1).0.y;
+    set_ball(&mut app, Vec2::new(13.0, y), Vec2::new(-40.0, 0.0));
+
+    app.update();
+    let (position, velocity) = ball(&mut app);
+    assert!(velocity.0.x > 0.0);
+    assert!(position.0.x >= 14.0);
+
+    for _ in 0..5 {
+        app.update();
+        assert!(ball(&mut app).1.0.x > 0.0);
+    }
+}
@@ -0,0 +1,264 @@
+use bevy::{
+    input::{
+        ButtonState,
+        gamepad::{RawGamepadButtonChangedEvent, RawGamepadEvent},
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use common::*;
+use first_bevy_game::pong::*;
+
+mod common;
+
+fn input_app(config: PongConfig) -> App {
+    headless_app(PongConfig {
+        seed: Some(2),
+        ..config
+    })
+}
+
+fn mode_app(mode: GameMode) -> App {
+    input_app(PongConfig {
+        mode,
+        start_delay: 0.0,
+        ..default()
+    })
+}
+
+fn is_ai(app: &mut App, player: u8) -> bool {
+    let mut query = app.world_mut().query::<(&Paddle, Has<AiController>)>();
+    query
+        .iter(app.world())
+        .find(|(paddle, _)| paddle.player == player)
+        .map(|(_, ai)| ai)
+        .unwrap()
+}
+
+fn keys(app: &mut App) -> Mut<'_, ButtonInput<KeyCode>> {
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>()
+}
+
+#[test]
+fn mode_decides_who_controls_paddles() {
+    let mut app = mode_app(GameMode::VsAi);
+    assert!(!is_ai(&mut app, 1));
+    assert!(is_ai(&mut app, 2));
+
+    let mut app = mode_app(GameMode::TwoPlayer);
+    assert!(!is_ai(&mut app, 1));
+    assert!(!is_ai(&mut app, 2));
+
+    let mut app = mode_app(GameMode::AiVsAi);
+    assert!(is_ai(&mut app, 1));
+    assert!(is_ai(&mut app, 2));
+}
+
+#[test]
+fn two_players_share_the_keyboard() {
+    let mut app = mode_app(GameMode::TwoPlayer);
+    keys(&mut app).press(KeyCode::KeyW);
+    keys(&mut app).press(KeyCode::ArrowDown);
+
+    run_for(&mut app, TICK * 10);
+    assert!(paddle(&mut app, 1).0.y > 50.0);
+    assert!(paddle(&mut app, 2).0.y < 50.0);
+}
+
+#[test]
+fn arrows_move_player_one_against_the_computer() {
+    let mut app = mode_app(GameMode::VsAi);
+    keys(&mut app).press(KeyCode::ArrowUp);
+
+    run_for(&mut app, TICK * 10);
+    assert!(paddle(&mut app, 1).0.y > 50.0);
+}
+
+#[test]
+fn remote_paddles_are_left_to_their_controller() {
+    let mut app = mode_app(GameMode::TwoPlayer);
+    let mut query = app.world_mut().query::<(Entity, &Paddle)>();
+    let remote = query
+        .iter(app.world())
+        .find(|(_, paddle)| paddle.player == 2)
+        .map(|(entity, _)| entity)
+        .unwrap();
+    app.world_mut().entity_mut(remote).insert(RemoteControlled);
+
+    // Neither the local arrow keys nor physics move it
+    keys(&mut app).press(KeyCode::ArrowDown);
+    app.world_mut().get_mut::<Velocity>(remote).unwrap().0 = Vec2::new(0.0, 80.0);
+    run_for(&mut app, TICK * 10);
+    assert_eq!(paddle(&mut app, 2).0.y, 50.0);
+    assert_eq!(
+        app.world().get::<Paddle>(remote).unwrap().input_direction.0,
+        Vec2::ZERO
+    );
+
+    // Whoever controls it moves it directly
+    app.world_mut().get_mut::<Position>(remote).unwrap().0.y = 70.0;
+    run_for(&mut app, TICK * 10);
+    assert_eq!(paddle(&mut app, 2).0.y, 70.0);
+}
+
+#[test]
+fn custom_bindings_replace_defaults() {
+    let mut bindings = InputBindings::for_mode(GameMode::TwoPlayer);
+    bindings.players.insert(
+        1,
+        PlayerBindings {
+            keys: vec![(KeyCode::KeyI, Action::MoveUp)],
+            ..default()
+        },
+    );
+    let mut app = input_app(PongConfig {
+        mode: GameMode::TwoPlayer,
+        bindings: Some(bindings),
+        start_delay: 0.0,
+        ..default()
+    });
+
+    keys(&mut app).press(KeyCode::KeyW);
+    run_for(&mut app, TICK * 10);
+    assert_eq!(paddle(&mut app, 1).0.y, 50.0);
+
+    keys(&mut app).press(KeyCode::KeyI);
+    run_for(&mut app, TICK * 10);
+    assert!(paddle(&mut app, 1).0.y > 50.0);
+}
+
+fn connect_gamepad(app: &mut App) -> Entity {
+    let entity = app.world_mut().spawn(Gamepad::default()).id();
+    app.update();
+    entity
+}
+
+fn slot(app: &App, gamepad: Entity) -> Option<u8> {
+    app.world().get::<GamepadSlot>(gamepad).map(|slot| slot.0)
+}
+
+#[test]
+fn gamepads_are_assigned_on_connect() {
+    let mut app = mode_app(GameMode::TwoPlayer);
+    let first = connect_gamepad(&mut app);
+    let second = connect_gamepad(&mut app);
+    let third = connect_gamepad(&mut app);
+
+    assert_eq!(slot(&app, first), Some(1));
+    assert_eq!(slot(&app, second), Some(2));
+    assert_eq!(slot(&app, third), None);
+
+    // Disconnecting frees the slot for the spare gamepad.
+    app.world_mut().entity_mut(first).remove::<Gamepad>();
+    app.update();
+    app.update();
+    assert_eq!(slot(&app, first), None);
+    assert_eq!(slot(&app, third), Some(1));
+}
+
+#[test]
+fn only_human_slots_get_gamepads() {
+    let mut app = mode_app(GameMode::VsAi);
+    let first = connect_gamepad(&mut app);
+    let second = connect_gamepad(&mut app);
+
+    assert_eq!(slot(&app, first), Some(1));
+    assert_eq!(slot(&app, second), None);
+}
+
+#[test]
+fn gamepad_moves_its_players_paddle() {
+    let mut app = mode_app(GameMode::TwoPlayer);
+    connect_gamepad(&mut app);
+    let second = connect_gamepad(&mut app);
+
+    app.world_mut()
+        .get_mut::<Gamepad>(second)
+        .unwrap()
+        .analog_mut()
+        .set(GamepadAxis::LeftStickY, 1.0);
+    run_for(&mut app, TICK * 10);
+    assert_eq!(paddle(&mut app, 1).0.y, 50.0);
+    assert!(paddle(&mut app, 2).0.y > 50.0);
+
+    app.world_mut()
+        .get_mut::<Gamepad>(second)
+        .unwrap()
+        .analog_mut()
+        .set(GamepadAxis::LeftStickY, 0.0);
+    let y = paddle(&mut app, 2).0.y;
+    app.world_mut()
+        .get_mut::<Gamepad>(second)
+        .unwrap()
+        .digital_mut()
+        .press(GamepadButton::DPadDown);
+    run_for(&mut app, TICK * 10);
+    assert!(paddle(&mut app, 2).0.y < y);
+}
+
+#[test]
+fn start_button_pauses() {
+    let mut app = mode_app(GameMode::VsAi);
+    let gamepad = connect_gamepad(&mut app);
+
+    app.world_mut()
+        .send_event(RawGamepadEvent::Button(RawGamepadButtonChangedEvent::new(
+            gamepad,
+            GamepadButton::Start,
+            1.0,
+        )));
+    app.update();
+    app.update();
+    assert_eq!(
+        *app.world().resource::<State<GameState>>().get(),
+        GameState::Paused
+    );
+}
+
+#[test]
+fn serve_skips_the_countdown() {
+    let mut app = input_app(PongConfig {
+        start_delay: 10.0,
+        ..default()
+    });
+    run_for(&mut app, TICK * 5);
+    assert_eq!(
+        *app.world().resource::<State<GamePhase>>().get(),
+        GamePhase::Starting
+    );
+
+    app.world_mut().send_event(KeyboardInput {
+        key_code: KeyCode::Space,
+        logical_key: Key::Space,
+        state: ButtonState::Pressed,
+        text: None,
+        repeat: false,
+        window: Entity::PLACEHOLDER,
+    });
+    run_for(&mut app, TICK * 5);
+    assert_eq!(
+        *app.world().resource::<State<GamePhase>>().get(),
+        GamePhase::Rally
+    );
+}
+
+#[test]
+fn stick_deflection_scales_paddle_speed() {
+    let moved = |value: f32| {
+        let mut app = mode_app(GameMode::VsAi);
+        let gamepad = connect_gamepad(&mut app);
+        app.world_mut()
+            .get_mut::<Gamepad>(gamepad)
+            .unwrap()
+            .analog_mut()
+            .set(GamepadAxis::LeftStickY, value);
+        run_for(&mut app, TICK * 10);
+        paddle(&mut app, 1).0.y - 50.0
+    };
+
+    assert_eq!(moved(0.1), 0.0, "inside the deadzone");
+    let half = moved(0.6);
+    let full = moved(1.0);
+    assert!(half > 0.0 && half < full, "half {half}, full {full}");
+    assert!(moved(-1.0) < 0.0);
+}
@@ -0,0 +1,139 @@
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use common::*;
+use first_bevy_game::pong::*;
+
+mod common;
+
+fn pause_app() -> App {
+    headless_app(PongConfig {
+        start_delay: 0.5,
+        seed: Some(11),
+        ..default()
+    })
+}
+
+fn tap_escape(app: &mut App) {
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        app.world_mut().send_event(KeyboardInput {
+            key_code: KeyCode::Escape,
+            logical_key: Key::Escape,
+            state,
+            text: None,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+}
+
+fn run_until_rally(app: &mut App) {
+    while *app.world().resource::<State<GamePhase>>().get() != GamePhase::Rally {
+        app.update();
+    }
+}
+
+fn game_state(app: &App) -> GameState {
+    *app.world().resource::<State<GameState>>().get()
+}
+
+fn press_button(app: &mut App, button: MenuButton) {
+    let mut query = app.world_mut().query::<(&MenuButton, &mut Interaction)>();
+    for (menu_button, mut interaction) in query.iter_mut(app.world_mut()) {
+        if *menu_button == button {
+            *interaction = Interaction::Pressed;
+        }
+    }
+    app.update();
+}
+
+#[test]
+fn escape_toggles_pause_once() {
+    let mut app = pause_app();
+    run_until_rally(&mut app);
+
+    tap_escape(&mut app);
+    assert_eq!(game_state(&app), GameState::Paused);
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+    let mut buttons = app.world_mut().query::<&MenuButton>();
+    assert_eq!(buttons.iter(app.world()).count(), 3);
+
+    tap_escape(&mut app);
+    assert_eq!(game_state(&app), GameState::Playing);
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    assert_eq!(buttons.iter(app.world()).count(), 0);
+}
+
+#[test]
+fn pause_freezes_the_rally() {
+    let mut app = pause_app();
+    run_until_rally(&mut app);
+
+    tap_escape(&mut app);
+    let frozen = ball(&mut app).0.0;
+    for _ in 0..30 {
+        app.update();
+    }
+    assert_eq!(ball(&mut app).0.0, frozen);
+
+    press_button(&mut app, MenuButton::Resume);
+    app.update();
+    assert_eq!(game_state(&app), GameState::Playing);
+    assert_eq!(
+        *app.world().resource::<State<GamePhase>>().get(),
+        GamePhase::Rally
+    );
+    for _ in 0..5 {
+        app.update();
+    }
+    assert_ne!(ball(&mut app).0.0, frozen);
+}
+
+#[test]
+fn restart_resets_match_through_countdown() {
+    let mut app = pause_app();
+    run_until_rally(&mut app);
+    for _ in 0..30 {
+        app.update();
+    }
+    app.world_mut().resource_mut::<Score>().ai = 3;
+
+    tap_escape(&mut app);
+    press_button(&mut app, MenuButton::Restart);
+    app.update();
+
+    assert_eq!(game_state(&app), GameState::Playing);
+    assert_eq!(
+        *app.world().resource::<State<GamePhase>>().get(),
+        GamePhase::Starting
+    );
+    assert_eq!(app.world().resource::<Score>().ai, 0);
+    assert_eq!(ball(&mut app).0.0, Vec2::new(50.0, 50.0));
+
+    let mut paddles = app.world_mut().query::<(&Paddle, &Position)>();
+    for (_, position) in paddles.iter(app.world()) {
+        assert_eq!(position.0.y, 50.0);
+    }
+
+    // The countdown runs again before the ball is served.
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(ball(&mut app).0.0, Vec2::new(50.0, 50.0));
+    run_until_rally(&mut app);
+}
+
+#[test]
+fn quit_exits_the_app() {
+    let mut app = pause_app();
+    tap_escape(&mut app);
+    press_button(&mut app, MenuButton::Quit);
+
+    assert_eq!(app.should_exit(), Some(AppExit::Success));
+}
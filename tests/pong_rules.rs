@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+use clap::Parser;
+use common::*;
+use first_bevy_game::pong::*;
+
+mod common;
+
+fn score(player: u32, ai: u32) -> Score {
+    Score { player, ai }
+}
+
+#[test]
+fn first_to_points_wins() {
+    let rules = MatchRules {
+        points_to_win: 5,
+        win_by_two: false,
+        ..default()
+    };
+
+    assert_eq!(rules.winner(&score(4, 4), 0.0), None);
+    assert_eq!(rules.winner(&score(5, 4), 0.0), Some(Scorer::Player));
+    assert_eq!(rules.winner(&score(2, 5), 0.0), Some(Scorer::Ai));
+}
+
+#[test]
+fn win_by_two_extends_the_match() {
+    let rules = MatchRules {
+        points_to_win: 5,
+        ..default()
+    };
+
+    assert_eq!(rules.winner(&score(5, 4), 0.0), None);
+    assert_eq!(rules.winner(&score(6, 5), 0.0), None);
+    assert_eq!(rules.winner(&score(7, 5), 0.0), Some(Scorer::Player));
+    assert_eq!(rules.winner(&score(5, 3), 0.0), Some(Scorer::Player));
+}
+
+#[test]
+fn time_limit_goes_to_leader_or_sudden_death() {
+    let rules = MatchRules {
+        time_limit: Some(60.0),
+        ..default()
+    };
+
+    assert_eq!(rules.winner(&score(3, 1), 59.0), None);
+    assert_eq!(rules.winner(&score(3, 1), 60.0), Some(Scorer::Player));
+    assert_eq!(rules.winner(&score(2, 2), 90.0), None);
+    assert_eq!(rules.winner(&score(2, 3), 90.0), Some(Scorer::Ai));
+}
+
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    rules: MatchRules,
+}
+
+#[test]
+fn rules_parse_from_command_line() {
+    let args = Args::parse_from(["pong"]);
+    assert_eq!(args.rules, MatchRules::default());
+
+    let args = Args::parse_from([
+        "pong",
+        "--points-to-win",
+        "3",
+        "--win-by-two",
+        "false",
+        "--serve",
+        "alternate",
+        "--time-limit",
+        "120",
+    ]);
+    assert_eq!(
+        args.rules,
+        MatchRules {
+            points_to_win: 3,
+            win_by_two: false,
+            serve: ServeRule::Alternate,
+            time_limit: Some(120.0),
+        }
+    );
+}
+
+fn rules_app(rules: MatchRules) -> App {
+    headless_app(PongConfig {
+        // A stationary ball so only the events we send score.
+        ball_speed: 0.0,
+        start_delay: 0.0,
+        score_delay: 0.1,
+        seed: Some(5),
+        rules,
+        ..default()
+    })
+}
+
+fn phase(app: &App) -> GamePhase {
+    app.world().resource::<State<GamePhase>>().get().clone()
+}
+
+fn run_until(app: &mut App, phase_wanted: GamePhase) {
+    for _ in 0..100 {
+        if phase(app) == phase_wanted {
+            return;
+        }
+        app.update();
+    }
+    panic!("stuck in {:?}", phase(app));
+}
+
+/// Scores a point during a rally and returns the phase it led to.
+fn point(app: &mut App, scorer: Scorer) -> GamePhase {
+    run_until(app, GamePhase::Rally);
+    app.world_mut().send_event(ScoredEvent(scorer));
+    while phase(app) == GamePhase::Rally {
+        app.update();
+    }
+    phase(app)
+}
+
+#[test]
+fn points_pass_through_scoring_phase() {
+    let mut app = rules_app(MatchRules::default());
+
+    assert_eq!(point(&mut app, Scorer::Player), GamePhase::Scoring);
+    run_until(&mut app, GamePhase::Rally);
+    assert_eq!(point(&mut app, Scorer::Ai), GamePhase::Scoring);
+
+    let score = app.world().resource::<Score>();
+    assert_eq!((score.player, score.ai), (1, 1));
+}
+
+#[test]
+fn match_ends_in_game_over() {
+    let mut app = rules_app(MatchRules {
+        points_to_win: 3,
+        ..default()
+    });
+
+    for _ in 0..2 {
+        assert_eq!(point(&mut app, Scorer::Ai), GamePhase::Scoring);
+    }
+    assert_eq!(point(&mut app, Scorer::Ai), GamePhase::GameOver);
+    assert_eq!(
+        app.world().resource::<MatchWinner>(),
+        &MatchWinner(Scorer::Ai)
+    );
+
+    // Nothing is served once the match is over.
+    for _ in 0..30 {
+        app.update();
+    }
+    assert_eq!(phase(&app), GamePhase::GameOver);
+    let mut buttons = app.world_mut().query::<&MenuButton>();
+    assert!(buttons.iter(app.world()).any(|b| *b == MenuButton::Rematch));
+}
+
+#[test]
+fn deuce_needs_two_clear_points() {
+    let mut app = rules_app(MatchRules {
+        points_to_win: 2,
+        ..default()
+    });
+
+    point(&mut app, Scorer::Player);
+    point(&mut app, Scorer::Ai);
+    assert_eq!(point(&mut app, Scorer::Player), GamePhase::Scoring);
+    assert_eq!(point(&mut app, Scorer::Ai), GamePhase::Scoring);
+    assert_eq!(point(&mut app, Scorer::Ai), GamePhase::Scoring);
+    assert_eq!(point(&mut app, Scorer::Ai), GamePhase::GameOver);
+}
+
+#[test]
+fn time_limit_ends_match_without_a_point() {
+    let mut app = rules_app(MatchRules {
+        time_limit: Some(1.0),
+        ..default()
+    });
+    point(&mut app, Scorer::Player);
+
+    run_until(&mut app, GamePhase::Rally);
+    run_until(&mut app, GamePhase::GameOver);
+    assert_eq!(
+        app.world().resource::<MatchWinner>(),
+        &MatchWinner(Scorer::Player)
+    );
+}
+
+#[test]
+fn rematch_starts_a_new_match() {
+    let mut app = rules_app(MatchRules {
+        points_to_win: 1,
+        win_by_two: false,
+        ..default()
+    });
+    assert_eq!(point(&mut app, Scorer::Player), GamePhase::GameOver);
+    app.update();
+
+    let mut buttons = app.world_mut().query::<(&MenuButton, &mut Interaction)>();
+    for (button, mut interaction) in buttons.iter_mut(app.world_mut()) {
+        if *button == MenuButton::Rematch {
+            *interaction = Interaction::Pressed;
+        }
+    }
+    app.update();
+    app.update();
+
+    assert_eq!(phase(&app), GamePhase::Starting);
+    let score = app.world().resource::<Score>();
+    assert_eq!((score.player, score.ai), (0, 0));
+    assert!(app.world().get_resource::<MatchWinner>().is_none());
+    assert_eq!(point(&mut app, Scorer::Ai), GamePhase::GameOver);
+}
+
+fn serve_direction(app: &mut App) -> f32 {
+    run_until(app, GamePhase::Rally);
+    ball(app).1.0.x.signum()
+}
+
+#[test]
+fn serve_follows_rule() {
+    let mut app = rules_app(MatchRules::default());
+    app.world_mut().resource_mut::<PongConfig>().ball_speed = 1.0;
+    point(&mut app, Scorer::Player);
+    assert_eq!(serve_direction(&mut app), 1.0);
+    point(&mut app, Scorer::Player);
+    assert_eq!(serve_direction(&mut app), 1.0);
+    point(&mut app, Scorer::Ai);
+    assert_eq!(serve_direction(&mut app), -1.0);
+
+    let mut app = rules_app(MatchRules {
+        serve: ServeRule::Alternate,
+        ..default()
+    });
+    app.world_mut().resource_mut::<PongConfig>().ball_speed = 1.0;
+    point(&mut app, Scorer::Player);
+    let first = serve_direction(&mut app);
+    point(&mut app, Scorer::Player);
+    assert_eq!(serve_direction(&mut app), -first);
+    point(&mut app, Scorer::Player);
+    assert_eq!(serve_direction(&mut app), first);
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+/// A snake in the middle of the board heading right.
+fn turn_app() -> App {
+    solo_app(SnakeConfig {
+        seed: Some(1),
+        ..default()
+    })
+}
+
+/// Keeps the snake from growing, so it can't run into itself.
+fn clear_food(app: &mut App) {
+    let mut food = app.world_mut().query_filtered::<Entity, With<Food>>();
+    for entity in food.iter(app.world()).collect::<Vec<_>>() {
+        app.world_mut().despawn(entity);
+    }
+}
+
+fn head(app: &mut App) -> (Position, Direction) {
+    let mut query = app.world_mut().query::<(&Position, &SnakeHead)>();
+    let (position, head) = query.single(app.world()).unwrap();
+    (*position, head.direction)
+}
+
+/// Runs one tick and returns the cell the head moved by.
+fn step(app: &mut App) -> (i16, i16) {
+    clear_food(app);
+    let (before, _) = head(app);
+    app.update();
+    let (after, _) = head(app);
+    // Wrapping at the edge moves the head across the whole board
+    let unwrap = |delta: i16| {
+        if delta.abs() > 1 {
+            -delta.signum()
+        } else {
+            delta
+        }
+    };
+    (unwrap(after.x - before.x), unwrap(after.y - before.y))
+}
+
+#[test]
+fn moves_one_cell_per_tick() {
+    let mut app = turn_app();
+    assert_eq!(step(&mut app), (1, 0));
+    assert_eq!(step(&mut app), (1, 0));
+}
+
+#[test]
+fn quick_turns_apply_on_consecutive_ticks() {
+    let mut app = turn_app();
+    // Up then left within a single tick, both turns are kept
+    press(&mut app, &[KeyCode::ArrowUp, KeyCode::ArrowLeft]);
+    assert_eq!(step(&mut app), (0, 1));
+    assert_eq!(step(&mut app), (-1, 0));
+    assert_eq!(step(&mut app), (-1, 0));
+}
+
+#[test]
+fn cannot_reverse_into_itself() {
+    let mut app = turn_app();
+    press(&mut app, &[KeyCode::ArrowLeft]);
+    assert_eq!(step(&mut app), (1, 0));
+    assert_eq!(head(&mut app).1, Direction::Right);
+
+    // Up, down, left within one tick: down would reverse the pending up turn
+    // so it is dropped, leaving a U-turn over two ticks
+    press(
+        &mut app,
+        &[KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft],
+    );
+    let moves: Vec<_> = (0..3).map(|_| step(&mut app)).collect();
+    assert_eq!(moves, [(0, 1), (-1, 0), (-1, 0)]);
+}
+
+#[test]
+fn never_reverses_whatever_the_sequence() {
+    let keys = [
+        KeyCode::ArrowUp,
+        KeyCode::ArrowDown,
+        KeyCode::ArrowLeft,
+        KeyCode::ArrowRight,
+    ];
+    let mut app = turn_app();
+    let mut last = (1, 0);
+    for round in 0..64usize {
+        let pressed: Vec<_> = (0..round % 4 + 1)
+            .map(|i| keys[(round * 7 + i * 3) % 4])
+            .collect();
+        press(&mut app, &pressed);
+        let moved = step(&mut app);
+        assert_ne!(
+            moved,
+            (-last.0, -last.1),
+            "reversed after pressing {pressed:?}"
+        );
+        last = moved;
+    }
+}
+
+#[test]
+fn queue_ignores_repeats_and_overflow() {
+    let mut queue = InputQueue::default();
+    queue.push(Direction::Up);
+    queue.push(Direction::Up);
+    queue.push(Direction::Left);
+    queue.push(Direction::Down);
+    queue.push(Direction::Right);
+    assert_eq!(
+        queue.0,
+        [Direction::Up, Direction::Left, Direction::Down].as_slice()
+    );
+
+    assert_eq!(queue.next_turn(Direction::Down), Some(Direction::Left));
+    assert_eq!(queue.next_turn(Direction::Left), Some(Direction::Down));
+    assert_eq!(queue.next_turn(Direction::Down), None);
+}
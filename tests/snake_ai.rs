@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+/// Every snake steered by the computer, without special food.
+fn ai_app(grid: Grid, players: u8, seed: u64) -> App {
+    snake_app(SnakeConfig {
+        grid,
+        seed: Some(seed),
+        special_food_chance: 0.0,
+        players,
+        ai_players: (1..=players).collect(),
+        ..default()
+    })
+}
+
+fn lengths(app: &mut App) -> Vec<usize> {
+    let mut query = app.world_mut().query::<&SnakeSegments>();
+    query
+        .iter(app.world())
+        .map(|segments| segments.0.len())
+        .collect()
+}
+
+/// Runs a game until it ends or `ticks` run out, returning the final length.
+fn play(app: &mut App, ticks: u32) -> usize {
+    for _ in 0..ticks {
+        if state(app) != SnakeState::Playing {
+            break;
+        }
+        app.update();
+    }
+    lengths(app)[0]
+}
+
+#[test]
+fn heads_straight_for_food() {
+    let mut app = ai_app(BoardPreset::Classic.grid(), 1, 1);
+    let mut query = app.world_mut().query::<(&mut Position, &mut SnakeHead)>();
+    let (mut position, mut head) = query.single_mut(app.world_mut()).unwrap();
+    *position = Position { x: 2, y: 2 };
+    head.direction = Direction::Up;
+
+    let mut food = app
+        .world_mut()
+        .query_filtered::<&mut Position, With<Food>>();
+    *food.single_mut(app.world_mut()).unwrap() = Position { x: 5, y: 4 };
+
+    // Three cells across and two up
+    for _ in 0..5 {
+        app.update();
+    }
+    assert_eq!(lengths(&mut app), [2]);
+}
+
+#[test]
+fn never_turns_back_into_its_neck() {
+    let mut app = ai_app(BoardPreset::Classic.grid(), 1, 1);
+    let mut heads = app.world_mut().query::<(&mut Position, &mut SnakeHead)>();
+    let (mut position, mut head) = heads.single_mut(app.world_mut()).unwrap();
+    *position = Position { x: 4, y: 2 };
+    head.direction = Direction::Right;
+    let mut food = app
+        .world_mut()
+        .query_filtered::<&mut Position, With<Food>>();
+    *food.single_mut(app.world_mut()).unwrap() = Position { x: 5, y: 2 };
+    app.update();
+    assert_eq!(lengths(&mut app), [2]);
+
+    // The shortest way to food right behind it leads through its own neck
+    *food.single_mut(app.world_mut()).unwrap() = Position { x: 3, y: 2 };
+    app.update();
+    let (position, _) = heads.single(app.world()).unwrap();
+    assert_ne!(*position, Position { x: 4, y: 2 });
+}
+
+#[test]
+fn walls_are_avoided() {
+    let mut grid = BoardPreset::Walled.grid();
+    grid.tick_rate = 10.0;
+    for seed in 0..3 {
+        let mut app = ai_app(grid, 1, seed);
+        play(&mut app, 300);
+        assert_eq!(state(&app), SnakeState::Playing, "seed {seed}");
+    }
+}
+
+#[test]
+fn ai_snakes_play_versus() {
+    let mut app = ai_app(BoardPreset::Big.grid(), 2, 3);
+    for _ in 0..200 {
+        app.update();
+    }
+    assert!(lengths(&mut app).iter().all(|length| *length > 1));
+}
+
+/// Solo games on the 12x12 board, capped at 80 seconds of play each. The AI
+/// gets past 80 cells in that time, a regression in its pathfinding or
+/// survival play shows up as a much shorter snake.
+#[test]
+fn benchmark_average_length_on_classic_board() {
+    const GAMES: u64 = 3;
+    const MIN_AVERAGE: f32 = 60.0;
+
+    let mut total = 0;
+    for seed in 0..GAMES {
+        let mut app = ai_app(BoardPreset::Classic.grid(), 1, seed);
+        total += play(&mut app, 800);
+    }
+    let average = total as f32 / GAMES as f32;
+    assert!(
+        average >= MIN_AVERAGE,
+        "average length {average} is below {MIN_AVERAGE}"
+    );
+}
+
+#[test]
+fn computer_runs_stay_out_of_the_high_scores() {
+    let mut app = ai_app(BoardPreset::Classic.grid(), 1, 1);
+    let mut query = app.world_mut().query_filtered::<Entity, With<AiSnake>>();
+    let snake = query.single(app.world()).unwrap();
+    app.world_mut().send_event(SnakeDied(snake));
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::GameOver);
+    assert!(app.world().resource::<HighScores>().entries.is_empty());
+}
+
+#[test]
+fn attract_mode_restarts_by_itself() {
+    let mut app = snake_app(SnakeConfig {
+        seed: Some(1),
+        attract: true,
+        ..default()
+    });
+
+    let mut query = app.world_mut().query_filtered::<Entity, With<AiSnake>>();
+    let snake = query.single(app.world()).unwrap();
+    app.world_mut().send_event(SnakeDied(snake));
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::GameOver);
+    assert!(app.world().resource::<HighScores>().entries.is_empty());
+
+    // A little over the three seconds the results stay up
+    for _ in 0..33 {
+        app.update();
+    }
+    assert_eq!(state(&app), SnakeState::Playing);
+    assert_eq!(query.iter(app.world()).count(), 1);
+}
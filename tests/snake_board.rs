@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use clap::Parser;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+fn board_app(grid: Grid) -> App {
+    snake_app(SnakeConfig {
+        grid,
+        seed: Some(4),
+        ..default()
+    })
+}
+
+fn head(app: &mut App) -> Position {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<&Position, With<SnakeHead>>();
+    *query.single(app.world()).unwrap()
+}
+
+/// Points the snake at the right hand edge, `cells` away from it.
+fn head_for_edge(app: &mut App, cells: i16) {
+    let width = app.world().resource::<Grid>().width;
+    let mut query = app.world_mut().query::<(&mut Position, &mut SnakeHead)>();
+    let (mut position, mut head) = query.single_mut(app.world_mut()).unwrap();
+    position.x = width - 1 - cells;
+    head.direction = Direction::Right;
+}
+
+#[test]
+fn steps_wrap_or_stop_at_the_edge() {
+    let wrapping = BoardPreset::Classic.grid();
+    let corner = Position { x: 11, y: 0 };
+    assert_eq!(
+        wrapping.step(corner, Direction::Right),
+        Some(Position { x: 0, y: 0 })
+    );
+    assert_eq!(
+        wrapping.step(corner, Direction::Down),
+        Some(Position { x: 11, y: 11 })
+    );
+
+    let walled = Grid {
+        edges: Edges::Solid,
+        ..wrapping
+    };
+    assert_eq!(walled.step(corner, Direction::Right), None);
+    assert_eq!(walled.step(corner, Direction::Down), None);
+    assert_eq!(
+        walled.step(corner, Direction::Left),
+        Some(Position { x: 10, y: 0 })
+    );
+}
+
+#[test]
+fn snake_wraps_on_wrapping_board() {
+    let mut app = board_app(BoardPreset::Classic.grid());
+    head_for_edge(&mut app, 0);
+    let y = head(&mut app).y;
+    app.update();
+    assert_eq!(head(&mut app), Position { x: 0, y });
+}
+
+#[test]
+fn snake_dies_on_walls() {
+    let mut app = board_app(BoardPreset::Walled.grid());
+    head_for_edge(&mut app, 1);
+    app.update();
+    assert_eq!(head(&mut app).x, 15);
+
+    // Running into the wall ends the run with the head against it
+    app.update();
+    app.update();
+    assert_eq!(head(&mut app).x, 15);
+    assert_eq!(state(&app), SnakeState::GameOver);
+}
+
+#[test]
+fn tick_rate_sets_fixed_timestep() {
+    let app = board_app(BoardPreset::Walled.grid());
+    assert_eq!(
+        app.world().resource::<Time<Fixed>>().timestep(),
+        Duration::from_millis(125)
+    );
+}
+
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    board: BoardArgs,
+}
+
+#[test]
+fn board_parses_from_command_line() {
+    let args = Args::parse_from(["snake"]);
+    assert_eq!(args.board.grid(), Grid::default());
+    assert_eq!(Grid::default(), BoardPreset::Classic.grid());
+
+    let args = Args::parse_from(["snake", "--preset", "big"]);
+    assert_eq!(args.board.grid(), BoardPreset::Big.grid());
+
+    let args = Args::parse_from([
+        "snake",
+        "--preset",
+        "walled",
+        "--width",
+        "20",
+        "--edges",
+        "wrap",
+        "--tick-rate",
+        "15",
+    ]);
+    assert_eq!(
+        args.board.grid(),
+        Grid {
+            width: 20,
+            height: 16,
+            edges: Edges::Wrap,
+            tick_rate: 15.0,
+        }
+    );
+}
+
+#[test]
+fn tick_rate_must_be_positive() {
+    for rate in ["0", "-4", "inf", "fast"] {
+        assert!(
+            Args::try_parse_from(["snake", "--tick-rate", rate]).is_err(),
+            "{rate}"
+        );
+    }
+    let args = Args::parse_from(["snake", "--tick-rate", "0.5"]);
+    assert_eq!(args.board.grid().tick_rate, 0.5);
+}
+
+#[test]
+fn boards_need_a_cell_either_way() {
+    for (arg, size) in [("--width", "0"), ("--height", "-3")] {
+        assert!(
+            Args::try_parse_from(["snake", arg, size]).is_err(),
+            "{arg} {size}"
+        );
+    }
+    let args = Args::parse_from(["snake", "--width", "1", "--height", "30"]);
+    assert_eq!((args.board.grid().width, args.board.grid().height), (1, 30));
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+/// A snake heading right that doesn't get any special food.
+fn death_app(grid: Grid) -> App {
+    solo_app(SnakeConfig {
+        grid,
+        seed: Some(5),
+        special_food_chance: 0.0,
+        ..default()
+    })
+}
+
+fn turn(app: &mut App, direction: Direction) {
+    let mut queue = app.world_mut().query::<&mut InputQueue>();
+    queue.single_mut(app.world_mut()).unwrap().push(direction);
+    app.update();
+}
+
+#[test]
+fn following_the_tail_is_safe() {
+    let mut app = death_app(Grid::default());
+    for _ in 0..3 {
+        feed(&mut app, FoodKind::Normal);
+    }
+    assert_eq!(snake_cells(&mut app).len(), 4);
+
+    // Loop round so the head lands on the cell the tail is leaving
+    turn(&mut app, Direction::Up);
+    turn(&mut app, Direction::Left);
+    let tail = *snake_cells(&mut app).last().unwrap();
+    turn(&mut app, Direction::Down);
+    assert_eq!(snake_cells(&mut app)[0], tail);
+    assert_eq!(state(&app), SnakeState::Playing);
+
+    turn(&mut app, Direction::Right);
+    assert_eq!(state(&app), SnakeState::Playing);
+}
+
+#[test]
+fn dies_on_body_across_a_wrapped_edge() {
+    let mut app = death_app(Grid {
+        width: 3,
+        height: 3,
+        ..default()
+    });
+    // Three segments fill the middle row, which the fourth grows into
+    for _ in 0..3 {
+        feed(&mut app, FoodKind::Normal);
+    }
+    assert_eq!(state(&app), SnakeState::Playing);
+
+    // Wrapping round onto the body ends the run, from the next frame on
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::GameOver);
+}
+
+#[test]
+fn game_over_freezes_until_restart() {
+    let mut app = death_app(Grid {
+        edges: Edges::Solid,
+        ..default()
+    });
+    for _ in 0..7 {
+        app.update();
+    }
+    assert_eq!(state(&app), SnakeState::GameOver);
+    let frozen = snake_cells(&mut app);
+    for _ in 0..5 {
+        app.update();
+    }
+    assert_eq!(snake_cells(&mut app), frozen);
+    let mut screens = app.world_mut().query::<&ResultsScreen>();
+    assert_eq!(screens.iter(app.world()).count(), 1);
+
+    press(&mut app, &[KeyCode::Space]);
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::Playing);
+    assert_eq!(screens.iter(app.world()).count(), 0);
+    assert_eq!(snake_cells(&mut app).len(), 1);
+}
@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+fn food_app(config: SnakeConfig) -> App {
+    solo_app(SnakeConfig {
+        seed: Some(7),
+        ..config
+    })
+}
+
+fn food(app: &mut App) -> Vec<(FoodKind, Position)> {
+    let mut query = app.world_mut().query::<(&Food, &Position)>();
+    query
+        .iter(app.world())
+        .map(|(food, position)| (food.kind, *position))
+        .collect()
+}
+
+#[test]
+fn food_never_spawns_on_the_snake() {
+    let mut app = food_app(SnakeConfig {
+        grid: Grid {
+            width: 5,
+            height: 5,
+            ..default()
+        },
+        special_food_chance: 0.5,
+        ..default()
+    });
+    for tick in 0..300 {
+        // Keep turning so the snake sweeps the board and eats often
+        if tick % 5 == 0 {
+            let mut queue = app.world_mut().query::<&mut InputQueue>();
+            let mut queue = queue.single_mut(app.world_mut()).unwrap();
+            queue.push(Direction::Up);
+            queue.push(Direction::Right);
+        }
+        if state(&app) != SnakeState::Playing {
+            app.world_mut().send_event(RestartGame);
+        }
+        app.update();
+
+        let snake = snake_cells(&mut app);
+        let food = food(&mut app);
+        assert!(
+            food.iter().any(|(kind, _)| *kind == FoodKind::Normal),
+            "no normal food on tick {tick}"
+        );
+        for (kind, position) in &food {
+            assert!(
+                !snake.contains(position),
+                "{kind:?} spawned on the snake at {position:?}"
+            );
+        }
+        for (i, (_, a)) in food.iter().enumerate() {
+            assert!(food[i + 1..].iter().all(|(_, b)| a != b), "stacked food");
+        }
+    }
+}
+
+#[test]
+fn filling_the_board_wins() {
+    let mut app = food_app(SnakeConfig {
+        grid: Grid {
+            width: 2,
+            height: 1,
+            ..default()
+        },
+        special_food_chance: 0.0,
+        ..default()
+    });
+    // The only free cell is the one next to the head
+    assert_eq!(
+        food(&mut app),
+        [(FoodKind::Normal, Position { x: 0, y: 0 })]
+    );
+    app.update();
+    app.update();
+
+    assert_eq!(snake_cells(&mut app).len(), 2);
+    assert_eq!(state(&app), SnakeState::Won);
+    assert!(food(&mut app).is_empty());
+}
+
+#[test]
+fn food_kinds_grow_and_shrink() {
+    let mut app = food_app(SnakeConfig {
+        special_food_chance: 0.0,
+        ..default()
+    });
+    feed(&mut app, FoodKind::Normal);
+    assert_eq!(snake_cells(&mut app).len(), 2);
+    feed(&mut app, FoodKind::Bonus);
+    assert_eq!(snake_cells(&mut app).len(), 4);
+    feed(&mut app, FoodKind::Shrink);
+    assert_eq!(snake_cells(&mut app).len(), 2);
+    feed(&mut app, FoodKind::Shrink);
+    assert_eq!(snake_cells(&mut app).len(), 1, "the head always stays");
+}
+
+#[test]
+fn speed_up_shortens_the_tick() {
+    let mut app = food_app(SnakeConfig {
+        special_food_chance: 0.0,
+        ..default()
+    });
+    let before = app.world().resource::<Time<Fixed>>().timestep();
+    feed(&mut app, FoodKind::SpeedUp);
+    let after = app.world().resource::<Time<Fixed>>().timestep();
+    assert!(after < before, "{after:?} is not faster than {before:?}");
+    assert_eq!(snake_cells(&mut app).len(), 2);
+}
+
+#[test]
+fn bonus_food_expires() {
+    let mut app = food_app(SnakeConfig {
+        special_food_chance: 0.0,
+        ..default()
+    });
+    // Out of the snake's way, on the row below
+    let bonus = app
+        .world_mut()
+        .spawn((Food::new(FoodKind::Bonus), Position { x: 0, y: 0 }))
+        .id();
+    let lifetime = FoodKind::Bonus.lifetime().unwrap();
+
+    for _ in 0..(lifetime * 10.0) as u32 - 1 {
+        app.update();
+    }
+    assert!(app.world().get_entity(bonus).is_ok());
+    app.update();
+    app.update();
+    assert!(app.world().get_entity(bonus).is_err());
+}
+
+#[test]
+fn special_food_appears_next_to_normal_food() {
+    let mut app = food_app(SnakeConfig {
+        special_food_chance: 1.0,
+        ..default()
+    });
+    let kinds: Vec<_> = food(&mut app).into_iter().map(|(kind, _)| kind).collect();
+    assert_eq!(kinds.len(), 2, "{kinds:?}");
+    assert!(kinds.contains(&FoodKind::Normal));
+    assert!(kinds.iter().any(|kind| FoodKind::SPECIAL.contains(kind)));
+}
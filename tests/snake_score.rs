@@ -0,0 +1,172 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+/// Empty directory for a test's save files, removed again once dropped, even
+/// when the test fails.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("snake_scores_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Self(dir)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(points: u32, length: u32, seconds: f32) -> HighScore {
+    HighScore {
+        points,
+        length,
+        seconds,
+    }
+}
+
+/// A snake heading right, saving its high scores to `high_score_file`.
+fn score_app(high_score_file: Option<PathBuf>) -> App {
+    solo_app(SnakeConfig {
+        seed: Some(3),
+        special_food_chance: 0.0,
+        high_score_file,
+        ..default()
+    })
+}
+
+#[test]
+fn table_is_sorted_and_capped() {
+    let mut scores = HighScores::default();
+    assert_eq!(scores.insert(run(5, 6, 30.0)), Some(0));
+    assert_eq!(scores.insert(run(9, 8, 50.0)), Some(0));
+    assert_eq!(scores.insert(run(5, 6, 20.0)), Some(1), "faster run first");
+    assert_eq!(
+        scores.insert(run(5, 7, 40.0)),
+        Some(1),
+        "longer snake first"
+    );
+    assert_eq!(scores.insert(run(1, 2, 5.0)), Some(4));
+
+    for _ in 0..HighScores::MAX_ENTRIES {
+        scores.insert(run(3, 4, 10.0));
+    }
+    assert_eq!(scores.entries.len(), HighScores::MAX_ENTRIES);
+    assert_eq!(scores.insert(run(0, 1, 1.0)), None);
+    assert_eq!(scores.entries[0], run(9, 8, 50.0));
+    assert!(!scores.entries.contains(&run(1, 2, 5.0)));
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let dir = ScratchDir::new("round_trip");
+    let path = dir.path().join("nested").join("scores.ron");
+    assert_eq!(HighScores::load(&path), HighScores::default());
+
+    let mut scores = HighScores::default();
+    scores.insert(run(12, 10, 61.5));
+    scores.insert(run(4, 5, 20.25));
+    scores.save(&path).unwrap();
+    assert_eq!(HighScores::load(&path), scores);
+
+    scores.insert(run(20, 15, 90.0));
+    scores.save(&path).unwrap();
+    assert_eq!(HighScores::load(&path), scores);
+}
+
+#[test]
+fn corrupted_file_is_moved_aside() {
+    let dir = ScratchDir::new("corrupted");
+    fs::create_dir_all(dir.path()).unwrap();
+    let path = dir.path().join("scores.ron");
+    fs::write(&path, "(entries: [(points: 3, length:").unwrap();
+
+    assert_eq!(HighScores::load(&path), HighScores::default());
+    assert!(!path.exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("scores.ron.corrupt")).unwrap(),
+        "(entries: [(points: 3, length:"
+    );
+
+    // Saving afterwards starts a fresh table
+    let mut scores = HighScores::default();
+    scores.insert(run(2, 3, 4.0));
+    scores.save(&path).unwrap();
+    assert_eq!(HighScores::load(&path), scores);
+}
+
+#[test]
+fn food_scores_points() {
+    let mut app = score_app(None);
+    feed(&mut app, FoodKind::Normal);
+    feed(&mut app, FoodKind::Bonus);
+    feed(&mut app, FoodKind::Shrink);
+
+    let mut score = app.world_mut().query::<&SnakeScore>();
+    assert_eq!(
+        score.single(app.world()).unwrap().points,
+        FoodKind::Normal.points() + FoodKind::Bonus.points() + FoodKind::Shrink.points()
+    );
+    let clock = app.world().resource::<RunClock>();
+    assert!((clock.seconds - 0.3).abs() < 1e-3, "{}", clock.seconds);
+
+    let mut hud = app.world_mut().query_filtered::<&Text, With<Hud>>();
+    let text = &hud.single(app.world()).unwrap().0;
+    assert!(text.starts_with("Score 8   Length 2"), "{text}");
+}
+
+#[test]
+fn dying_records_the_run() {
+    let dir = ScratchDir::new("dying");
+    let path = dir.path().join("scores.ron");
+    let mut app = score_app(Some(path.clone()));
+    for _ in 0..4 {
+        feed(&mut app, FoodKind::Normal);
+    }
+
+    // Turn back into the body
+    let mut queue = app.world_mut().query::<&mut InputQueue>();
+    let mut queue = queue.single_mut(app.world_mut()).unwrap();
+    queue.push(Direction::Up);
+    queue.push(Direction::Left);
+    queue.push(Direction::Down);
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let expected = run(4, 5, 0.7);
+    let scores = app.world().resource::<HighScores>().clone();
+    assert_eq!(scores.entries.len(), 1);
+    assert_eq!(scores.entries[0].points, expected.points);
+    assert_eq!(scores.entries[0].length, expected.length);
+    assert_eq!(HighScores::load(&path), scores);
+
+    let mut screens = app.world_mut().query::<&ResultsScreen>();
+    assert_eq!(screens.iter(app.world()).count(), 1);
+
+    // Restarting clears the results and the score
+    app.world_mut().send_event(RestartGame);
+    app.update();
+    app.update();
+    assert_eq!(screens.iter(app.world()).count(), 0);
+    let mut score = app.world_mut().query::<&SnakeScore>();
+    assert_eq!(score.single(app.world()).unwrap().points, 0);
+
+    // A new app picks the table up again
+    let app = score_app(Some(path));
+    assert_eq!(*app.world().resource::<HighScores>(), scores);
+}
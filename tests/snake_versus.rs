@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use common::snake::*;
+use first_bevy_game::snake::*;
+
+mod common;
+
+/// Versus on the classic board, without special food.
+fn versus_app(players: u8) -> App {
+    snake_app(SnakeConfig {
+        seed: Some(2),
+        special_food_chance: 0.0,
+        players,
+        ..default()
+    })
+}
+
+fn snake(app: &mut App, player: u8) -> Entity {
+    let mut query = app.world_mut().query::<(Entity, &SnakeHead)>();
+    query
+        .iter(app.world())
+        .find(|(_, head)| head.player == player)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+/// Moves food out of the way, into the corner no test goes near.
+fn park_food(app: &mut App) {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<&mut Position, With<Food>>();
+    for mut position in query.iter_mut(app.world_mut()) {
+        *position = Position { x: 0, y: 0 };
+    }
+}
+
+/// Lays a player's snake over `cells`, head first, heading in `direction`.
+fn place(app: &mut App, player: u8, cells: &[(i16, i16)], direction: Direction) {
+    let head = snake(app, player);
+    let mut segments = vec![head];
+    for &(x, y) in &cells[1..] {
+        segments.push(
+            app.world_mut()
+                .spawn((SnakeSegment, Position { x, y }))
+                .id(),
+        );
+    }
+    let (x, y) = cells[0];
+    let mut entity = app.world_mut().entity_mut(head);
+    *entity.get_mut::<Position>().unwrap() = Position { x, y };
+    entity.get_mut::<SnakeHead>().unwrap().direction = direction;
+    entity.insert(SnakeSegments(segments));
+    park_food(app);
+}
+
+fn direction(app: &mut App, player: u8) -> Direction {
+    let entity = snake(app, player);
+    app.world().get::<SnakeHead>(entity).unwrap().direction
+}
+
+fn is_dead(app: &mut App, player: u8) -> bool {
+    let entity = snake(app, player);
+    app.world().get::<Dead>(entity).is_some()
+}
+
+fn winner(app: &App) -> Option<SnakeWinner> {
+    app.world().get_resource::<SnakeWinner>().copied()
+}
+
+#[test]
+fn snakes_start_apart_in_their_colours() {
+    let mut app = versus_app(4);
+    let mut query = app.world_mut().query::<(&SnakeHead, &Position, &Sprite)>();
+    let mut heads: Vec<_> = query
+        .iter(app.world())
+        .map(|(head, position, sprite)| (head.player, *position, sprite.color))
+        .collect();
+    heads.sort_by_key(|(player, ..)| *player);
+
+    assert_eq!(heads.len(), 4);
+    for (player, position, color) in &heads {
+        assert_eq!(*color, player_color(*player));
+        let others = heads.iter().filter(|(other, ..)| other != player);
+        assert!(others.clone().all(|(_, other, _)| other != position));
+    }
+}
+
+#[test]
+fn each_player_steers_their_own_snake() {
+    let mut app = versus_app(2);
+    place(&mut app, 1, &[(3, 3)], Direction::Right);
+    place(&mut app, 2, &[(8, 8)], Direction::Left);
+
+    press(&mut app, &[KeyCode::KeyW]);
+    press(&mut app, &[KeyCode::ArrowDown]);
+    app.update();
+    assert_eq!(direction(&mut app, 1), Direction::Up);
+    assert_eq!(direction(&mut app, 2), Direction::Down);
+
+    // Player 2's keys never touch player 1
+    press(&mut app, &[KeyCode::ArrowLeft]);
+    app.update();
+    assert_eq!(direction(&mut app, 1), Direction::Up);
+    assert_eq!(direction(&mut app, 2), Direction::Left);
+}
+
+#[test]
+fn running_into_another_snake_kills_the_runner() {
+    let mut app = versus_app(2);
+    place(&mut app, 1, &[(4, 5)], Direction::Right);
+    place(
+        &mut app,
+        2,
+        &[(5, 4), (5, 5), (5, 6), (5, 7)],
+        Direction::Down,
+    );
+
+    app.update();
+    assert!(is_dead(&mut app, 1));
+    assert!(!is_dead(&mut app, 2));
+
+    app.update();
+    assert_eq!(state(&app), SnakeState::GameOver);
+    assert_eq!(winner(&app), Some(SnakeWinner(Some(2))));
+    let mut screens = app.world_mut().query::<&ResultsScreen>();
+    assert_eq!(screens.iter(app.world()).count(), 1);
+
+    // Versus games stay out of the high score table
+    assert!(app.world().resource::<HighScores>().entries.is_empty());
+}
+
+#[test]
+fn heads_meeting_is_a_draw() {
+    let mut app = versus_app(2);
+    place(&mut app, 1, &[(4, 6)], Direction::Right);
+    place(&mut app, 2, &[(6, 6)], Direction::Left);
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::GameOver);
+    assert_eq!(winner(&app), Some(SnakeWinner(None)));
+
+    // Passing through each other counts too
+    app.world_mut().send_event(RestartGame);
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::Playing);
+    assert_eq!(winner(&app), None);
+    place(&mut app, 1, &[(4, 6)], Direction::Right);
+    place(&mut app, 2, &[(5, 6)], Direction::Left);
+    app.update();
+    assert!(is_dead(&mut app, 1));
+    assert!(is_dead(&mut app, 2));
+}
+
+#[test]
+fn last_snake_standing_wins() {
+    let mut app = versus_app(3);
+    place(&mut app, 1, &[(2, 9)], Direction::Right);
+    place(&mut app, 2, &[(3, 8), (3, 9), (3, 10)], Direction::Down);
+    place(&mut app, 3, &[(9, 2)], Direction::Left);
+
+    // Player 1 runs into player 2's body and stays there as an obstacle
+    app.update();
+    assert!(is_dead(&mut app, 1));
+    app.update();
+    assert_eq!(state(&app), SnakeState::Playing);
+    let dead_head = snake(&mut app, 1);
+    assert_eq!(
+        *app.world().get::<Position>(dead_head).unwrap(),
+        Position { x: 3, y: 9 }
+    );
+
+    // Player 3 runs into the dead snake
+    place(&mut app, 3, &[(4, 9)], Direction::Left);
+    app.update();
+    app.update();
+    assert_eq!(state(&app), SnakeState::GameOver);
+    assert_eq!(winner(&app), Some(SnakeWinner(Some(2))));
+}
+
+#[test]
+fn scores_are_kept_per_snake() {
+    let mut app = versus_app(2);
+    place(&mut app, 1, &[(2, 2)], Direction::Right);
+    place(&mut app, 2, &[(8, 8)], Direction::Left);
+    app.world_mut()
+        .spawn((Food::new(FoodKind::Normal), Position { x: 7, y: 8 }));
+    app.update();
+
+    let (one, two) = (snake(&mut app, 1), snake(&mut app, 2));
+    assert_eq!(app.world().get::<SnakeScore>(one).unwrap().points, 0);
+    assert_eq!(
+        app.world().get::<SnakeScore>(two).unwrap().points,
+        FoodKind::Normal.points()
+    );
+    app.update();
+    assert_eq!(app.world().get::<SnakeSegments>(one).unwrap().0.len(), 1);
+    assert_eq!(app.world().get::<SnakeSegments>(two).unwrap().0.len(), 2);
+}
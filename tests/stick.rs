@@ -0,0 +1,80 @@
+use bevy::{math::CompassQuadrant, prelude::*};
+use first_bevy_game::stick::*;
+
+fn linear() -> StickSettings {
+    StickSettings {
+        deadzone: 0.2,
+        outer_deadzone: 0.8,
+        curve: ResponseCurve::Linear,
+        ..default()
+    }
+}
+
+#[test]
+fn axis_deadzones() {
+    let stick = linear();
+    assert_eq!(stick.axis(0.0), 0.0);
+    assert_eq!(stick.axis(0.15), 0.0);
+    assert_eq!(stick.axis(-0.2), 0.0);
+    assert!((stick.axis(0.5) - 0.5).abs() < 1e-6);
+    assert!((stick.axis(-0.5) + 0.5).abs() < 1e-6);
+    assert_eq!(stick.axis(0.9), 1.0);
+    assert_eq!(stick.axis(-1.0), -1.0);
+}
+
+#[test]
+fn power_curve_softens_small_deflections() {
+    let curved = StickSettings {
+        curve: ResponseCurve::Power(2.0),
+        ..linear()
+    };
+    assert!((curved.axis(0.5) - 0.25).abs() < 1e-6);
+    assert_eq!(curved.axis(1.0), 1.0);
+    assert!(curved.axis(0.5) < linear().axis(0.5));
+}
+
+#[test]
+fn radial_deadzone_keeps_direction() {
+    let stick = linear();
+    assert_eq!(stick.stick(Vec2::new(0.1, 0.1)), Vec2::ZERO);
+    let diagonal = stick.stick(Vec2::splat(0.9));
+    assert!((diagonal.length() - 1.0).abs() < 1e-6);
+    assert!((diagonal.x - diagonal.y).abs() < 1e-6);
+}
+
+#[test]
+fn quantizes_to_nearest_direction() {
+    let stick = linear();
+    assert_eq!(stick.quantize(Vec2::new(0.05, 0.0), None), None);
+    assert_eq!(
+        stick.quantize(Vec2::new(0.9, 0.2), None),
+        Some(CompassQuadrant::East)
+    );
+    assert_eq!(
+        stick.quantize(Vec2::new(-0.1, -0.9), None),
+        Some(CompassQuadrant::South)
+    );
+}
+
+#[test]
+fn hysteresis_ignores_diagonal_wobble() {
+    let stick = StickSettings {
+        hysteresis: 10f32.to_radians(),
+        ..linear()
+    };
+    // Just past the diagonal towards north, but still within the hysteresis
+    // band of east.
+    let wobble = Vec2::from_angle(50f32.to_radians()) * 0.9;
+    assert_eq!(
+        stick.quantize(wobble, Some(CompassQuadrant::East)),
+        Some(CompassQuadrant::East)
+    );
+    assert_eq!(stick.quantize(wobble, None), Some(CompassQuadrant::North));
+
+    // Pushed clearly past the band it switches.
+    let turned = Vec2::from_angle(60f32.to_radians()) * 0.9;
+    assert_eq!(
+        stick.quantize(turned, Some(CompassQuadrant::East)),
+        Some(CompassQuadrant::North)
+    );
+}